@@ -0,0 +1,258 @@
+use crate::services::ChangeHandlers;
+use gtk::prelude::*;
+use gtk::Window;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// `~/.config/gtk-3.0/bookmarks`, shared with the GTK file chooser and other
+/// file managers.
+fn bookmarks_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from(".config"))
+        .join("gtk-3.0")
+        .join("bookmarks")
+}
+
+/// One line of the bookmarks file: a URI and an optional label.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bookmark {
+    pub uri: String,
+    pub label: Option<String>,
+}
+
+impl Bookmark {
+    pub fn for_path(path: &Path) -> Self {
+        Bookmark {
+            uri: gio::File::for_path(path).uri().to_string(),
+            label: None,
+        }
+    }
+
+    /// The local folder, or `None` for remote locations such as `sftp://`.
+    pub fn path(&self) -> Option<PathBuf> {
+        gio::File::for_uri(&self.uri).path()
+    }
+
+    pub fn display_name(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        let file = gio::File::for_uri(&self.uri);
+        file.basename()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.uri.clone())
+    }
+}
+
+/// Bookmarked folders, shared so that every window's sidebar stays in sync.
+/// Changes made by other programs are picked up through a file monitor.
+#[derive(Clone)]
+pub struct Bookmarks {
+    bookmarks: Rc<RefCell<Vec<Bookmark>>>,
+    handlers: ChangeHandlers,
+    monitor: Rc<RefCell<Option<gio::FileMonitor>>>,
+}
+
+impl Bookmarks {
+    pub fn load() -> Self {
+        let bookmarks = Bookmarks {
+            bookmarks: Rc::new(RefCell::new(read_bookmarks())),
+            handlers: ChangeHandlers::new(),
+            monitor: Rc::new(RefCell::new(None)),
+        };
+        bookmarks.watch();
+        bookmarks
+    }
+
+    fn watch(&self) {
+        let file = gio::File::for_path(bookmarks_path());
+        let monitor = match file.monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE) {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                eprintln!("Failed to watch bookmarks: {}", e);
+                return;
+            }
+        };
+
+        let bookmarks = self.clone();
+        monitor.connect_changed(move |_, _, _, event| {
+            if matches!(
+                event,
+                gio::FileMonitorEvent::ChangesDoneHint
+                    | gio::FileMonitorEvent::Created
+                    | gio::FileMonitorEvent::Deleted
+            ) {
+                bookmarks.reload();
+            }
+        });
+        *self.monitor.borrow_mut() = Some(monitor);
+    }
+
+    /// Re-reads the file after an outside change. Our own writes read back
+    /// unchanged and are ignored.
+    fn reload(&self) {
+        let bookmarks = read_bookmarks();
+        if *self.bookmarks.borrow() == bookmarks {
+            return;
+        }
+        *self.bookmarks.borrow_mut() = bookmarks;
+        self.notify_changed();
+    }
+
+    pub fn list(&self) -> Vec<Bookmark> {
+        self.bookmarks.borrow().clone()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        let uri = Bookmark::for_path(path).uri;
+        self.bookmarks.borrow().iter().any(|b| b.uri == uri)
+    }
+
+    pub fn add(&self, path: &Path) {
+        if self.contains(path) {
+            return;
+        }
+        self.bookmarks.borrow_mut().push(Bookmark::for_path(path));
+        self.changed();
+    }
+
+    pub fn remove(&self, uri: &str) {
+        self.bookmarks.borrow_mut().retain(|b| b.uri != uri);
+        self.changed();
+    }
+
+    /// Sets the label shown instead of the folder name; `None` restores it.
+    pub fn rename(&self, uri: &str, label: Option<String>) {
+        if let Some(bookmark) = self
+            .bookmarks
+            .borrow_mut()
+            .iter_mut()
+            .find(|b| b.uri == uri)
+        {
+            bookmark.label = label;
+        }
+        self.changed();
+    }
+
+    /// Moves the bookmark `uri` to the position of `target_uri`.
+    pub fn move_to(&self, uri: &str, target_uri: &str) {
+        {
+            let mut bookmarks = self.bookmarks.borrow_mut();
+            let Some(from) = bookmarks.iter().position(|b| b.uri == uri) else {
+                return;
+            };
+            let Some(to) = bookmarks.iter().position(|b| b.uri == target_uri) else {
+                return;
+            };
+            if from == to {
+                return;
+            }
+            let bookmark = bookmarks.remove(from);
+            bookmarks.insert(to, bookmark);
+        }
+        self.changed();
+    }
+
+    pub fn connect_changed<F>(&self, callback: F) -> usize
+    where
+        F: Fn() + 'static,
+    {
+        self.handlers.connect(move |_| callback())
+    }
+
+    pub fn disconnect(&self, handler_id: usize) {
+        self.handlers.disconnect(handler_id);
+    }
+
+    fn changed(&self) {
+        if let Err(e) = save(&self.bookmarks.borrow()) {
+            eprintln!("Failed to save bookmarks: {}", e);
+        }
+        self.notify_changed();
+    }
+
+    fn notify_changed(&self) {
+        self.handlers.notify(&());
+    }
+}
+
+fn read_bookmarks() -> Vec<Bookmark> {
+    let Ok(contents) = fs::read_to_string(bookmarks_path()) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match line.split_once(' ') {
+            Some((uri, label)) => Bookmark {
+                uri: uri.to_string(),
+                label: Some(label.to_string()),
+            },
+            None => Bookmark {
+                uri: line.to_string(),
+                label: None,
+            },
+        })
+        .collect()
+}
+
+fn save(bookmarks: &[Bookmark]) -> std::io::Result<()> {
+    let path = bookmarks_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents: String = bookmarks
+        .iter()
+        .map(|bookmark| match &bookmark.label {
+            Some(label) => format!("{} {}\n", bookmark.uri, label),
+            None => format!("{}\n", bookmark.uri),
+        })
+        .collect();
+    fs::write(path, contents)
+}
+
+/// Asks for a new bookmark label. An empty name restores the folder name.
+pub fn show_rename_dialog<F>(parent: &impl IsA<Window>, bookmark: &Bookmark, on_rename: F)
+where
+    F: Fn(Option<String>) + 'static,
+{
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Rename Bookmark"),
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::USE_HEADER_BAR,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Rename", gtk::ResponseType::Accept),
+        ],
+    );
+
+    let content_area = dialog.content_area();
+    content_area.set_margin_start(12);
+    content_area.set_margin_end(12);
+    content_area.set_margin_top(12);
+    content_area.set_margin_bottom(12);
+
+    let label = gtk::Label::new(Some("Name:"));
+    label.set_halign(gtk::Align::Start);
+    content_area.append(&label);
+
+    let entry = gtk::Entry::new();
+    entry.set_text(&bookmark.display_name());
+    entry.set_activates_default(true);
+    content_area.append(&entry);
+
+    dialog.set_default_response(gtk::ResponseType::Accept);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            let name = entry.text().trim().to_string();
+            on_rename((!name.is_empty()).then_some(name));
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
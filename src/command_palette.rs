@@ -0,0 +1,177 @@
+use crate::fuzzy;
+use crate::palette::{Palette, PaletteItem};
+use gtk::prelude::*;
+use gtk::ApplicationWindow;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// An action that can be run from the command palette.
+#[derive(Clone)]
+struct Command {
+    /// Prefixed action name, such as `win.copy`.
+    action: String,
+    target: Option<glib::Variant>,
+    title: String,
+}
+
+/// Ctrl+Shift+P palette listing every enabled `app.*` and `win.*` action.
+/// Actions in the window menu use their menu labels; any others are named
+/// after the action, so newly added actions show up without extra work.
+#[derive(Clone)]
+pub struct CommandPalette {
+    palette: Palette,
+}
+
+impl CommandPalette {
+    pub fn new(
+        parent: &impl IsA<gtk::Widget>,
+        window: &ApplicationWindow,
+        menu: &gio::MenuModel,
+    ) -> Self {
+        let palette = Palette::new(parent, "Run a command…");
+        let listed: Rc<RefCell<Vec<Command>>> = Rc::new(RefCell::new(Vec::new()));
+
+        let window_weak = window.downgrade();
+        let menu = menu.clone();
+        let listed_clone = listed.clone();
+        palette.connect_items(move |pattern| {
+            let Some(window) = window_weak.upgrade() else {
+                return Vec::new();
+            };
+
+            let mut ranked: Vec<(i64, Command)> = commands(&window, &menu)
+                .into_iter()
+                .filter_map(|command| {
+                    fuzzy::score(pattern, &command.title).map(|score| (score, command))
+                })
+                .collect();
+            // Stable, so an empty pattern keeps the menu order
+            ranked.sort_by(|a, b| b.0.cmp(&a.0));
+
+            let items = ranked
+                .iter()
+                .map(|(_, command)| PaletteItem {
+                    title: command.title.clone(),
+                    subtitle: String::new(),
+                    icon_name: "system-run-symbolic",
+                    shortcut: shortcut_label(&window, command),
+                })
+                .collect();
+            *listed_clone.borrow_mut() = ranked.into_iter().map(|(_, command)| command).collect();
+            items
+        });
+
+        // Runs in the window, so actions see its current folder and selection
+        let window_weak = window.downgrade();
+        palette.connect_activated(move |index| {
+            let command = listed.borrow().get(index).cloned();
+            if let (Some(window), Some(command)) = (window_weak.upgrade(), command) {
+                if let Err(e) =
+                    WidgetExt::activate_action(&window, &command.action, command.target.as_ref())
+                {
+                    eprintln!("Failed to run {}: {}", command.action, e);
+                }
+            }
+        });
+
+        CommandPalette { palette }
+    }
+
+    pub fn popup(&self) {
+        self.palette.popup();
+    }
+}
+
+/// Lists the enabled commands: menu entries first, in menu order, then the
+/// remaining actions that take no parameter.
+fn commands(window: &ApplicationWindow, menu: &gio::MenuModel) -> Vec<Command> {
+    let mut commands = Vec::new();
+    collect_menu_commands(menu, None, &mut commands);
+
+    let mut groups: Vec<(&str, gio::ActionGroup)> = vec![("win", window.clone().upcast())];
+    if let Some(app) = window.application() {
+        groups.push(("app", app.upcast()));
+    }
+    for (prefix, group) in &groups {
+        for name in group.list_actions() {
+            let action = format!("{}.{}", prefix, name);
+            let in_menu = commands.iter().any(|command| command.action == action);
+            if in_menu || group.action_parameter_type(&name).is_some() {
+                continue;
+            }
+            commands.push(Command {
+                title: title_from_name(&name),
+                action,
+                target: None,
+            });
+        }
+    }
+
+    commands.retain(|command| {
+        let Some((prefix, name)) = command.action.split_once('.') else {
+            return false;
+        };
+        groups
+            .iter()
+            .find(|(group_prefix, _)| *group_prefix == prefix)
+            .is_some_and(|(_, group)| group.is_action_enabled(name))
+            && name != "command-palette"
+    });
+    commands
+}
+
+/// Walks sections and submenus, titling submenu entries "Submenu: Entry".
+fn collect_menu_commands(
+    model: &gio::MenuModel,
+    parent: Option<&str>,
+    commands: &mut Vec<Command>,
+) {
+    for index in 0..model.n_items() {
+        let label = model
+            .item_attribute_value(index, "label", Some(glib::VariantTy::STRING))
+            .and_then(|value| value.get::<String>())
+            .map(|label| label.trim_end_matches('…').to_string());
+        let action = model
+            .item_attribute_value(index, "action", Some(glib::VariantTy::STRING))
+            .and_then(|value| value.get::<String>());
+        let target = model.item_attribute_value(index, "target", None);
+
+        if let (Some(label), Some(action)) = (&label, action) {
+            let title = match parent {
+                Some(parent) => format!("{}: {}", parent, label),
+                None => label.clone(),
+            };
+            commands.push(Command {
+                action,
+                target,
+                title,
+            });
+        }
+
+        if let Some(section) = model.item_link(index, "section") {
+            collect_menu_commands(&section, parent, commands);
+        }
+        if let Some(submenu) = model.item_link(index, "submenu") {
+            collect_menu_commands(&submenu, label.as_deref(), commands);
+        }
+    }
+}
+
+/// Turns `new-folder` into "New folder".
+fn title_from_name(name: &str) -> String {
+    let words = name.replace('-', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The first keyboard shortcut for `command`, e.g. "Ctrl+Shift+N".
+fn shortcut_label(window: &ApplicationWindow, command: &Command) -> Option<String> {
+    let app = window.application()?;
+    let detailed = gio::Action::print_detailed_name(&command.action, command.target.as_ref());
+    let accels = app.accels_for_action(&detailed);
+    let (key, modifiers) = gtk::accelerator_parse(accels.first()?)?;
+    Some(gtk::accelerator_get_label(key, modifiers).to_string())
+}
@@ -0,0 +1,444 @@
+use crate::operations;
+use crate::services::ChangeHandlers;
+use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// How often `/proc/self/mountinfo` is re-read when GVfs is not running.
+const MOUNTINFO_POLL_SECONDS: u32 = 3;
+
+/// What a device entry refers to, and so which operations it supports.
+#[derive(Clone)]
+enum Backing {
+    Mount(gio::Mount),
+    /// A volume that is not mounted yet.
+    Volume(gio::Volume),
+    /// A drive with media but no volumes, e.g. an unformatted card.
+    Drive(gio::Drive),
+    /// A mount point read from `/proc/self/mountinfo`.
+    Unix,
+}
+
+/// A filesystem listed in the sidebar's Devices section.
+#[derive(Clone)]
+pub struct Device {
+    pub name: String,
+    pub icon: Option<gio::Icon>,
+    /// Mount point, or `None` while unmounted.
+    pub path: Option<PathBuf>,
+    backing: Backing,
+}
+
+impl Device {
+    pub fn is_mounted(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn can_unmount(&self) -> bool {
+        match &self.backing {
+            Backing::Mount(mount) => mount.can_unmount(),
+            _ => false,
+        }
+    }
+
+    pub fn can_eject(&self) -> bool {
+        match &self.backing {
+            Backing::Mount(mount) => mount.can_eject(),
+            Backing::Volume(volume) => volume.can_eject(),
+            Backing::Drive(drive) => drive.can_eject(),
+            Backing::Unix => false,
+        }
+    }
+
+    /// Mounts the device, asking for passwords through `window`, and calls
+    /// `on_mounted` with the mount point.
+    pub fn mount<F>(&self, window: &gtk::Window, on_mounted: F)
+    where
+        F: Fn(PathBuf) + 'static,
+    {
+        if let Some(path) = &self.path {
+            on_mounted(path.clone());
+            return;
+        }
+        let Backing::Volume(volume) = &self.backing else {
+            return;
+        };
+
+        let operation = gtk::MountOperation::new(Some(window));
+        let name = self.name.clone();
+        let window = window.clone();
+        let mounted_volume = volume.clone();
+        volume.mount(
+            gio::MountMountFlags::NONE,
+            Some(&operation),
+            gio::Cancellable::NONE,
+            move |result| {
+                if let Err(e) = result {
+                    show_failure(&window, &format!("Failed to mount {}", name), &e);
+                    return;
+                }
+                if let Some(path) = mounted_volume.get_mount().and_then(|m| m.root().path()) {
+                    on_mounted(path);
+                }
+            },
+        );
+    }
+
+    pub fn unmount(&self, window: &gtk::Window) {
+        let Backing::Mount(mount) = &self.backing else {
+            return;
+        };
+        let operation = gtk::MountOperation::new(Some(window));
+        let name = self.name.clone();
+        let window = window.clone();
+        mount.unmount_with_operation(
+            gio::MountUnmountFlags::NONE,
+            Some(&operation),
+            gio::Cancellable::NONE,
+            move |result| {
+                if let Err(e) = result {
+                    show_failure(&window, &format!("Failed to unmount {}", name), &e);
+                }
+            },
+        );
+    }
+
+    /// Unmounts if needed and ejects the media or powers the drive down.
+    pub fn eject(&self, window: &gtk::Window) {
+        let operation = gtk::MountOperation::new(Some(window));
+        let name = self.name.clone();
+        let parent = window.clone();
+        let on_done = move |result: Result<(), glib::Error>| {
+            if let Err(e) = result {
+                show_failure(&parent, &format!("Failed to eject {}", name), &e);
+            }
+        };
+        let flags = gio::MountUnmountFlags::NONE;
+        match &self.backing {
+            Backing::Mount(mount) => {
+                mount.eject_with_operation(flags, Some(&operation), gio::Cancellable::NONE, on_done)
+            }
+            Backing::Volume(volume) => volume.eject_with_operation(
+                flags,
+                Some(&operation),
+                gio::Cancellable::NONE,
+                on_done,
+            ),
+            Backing::Drive(drive) => {
+                drive.eject_with_operation(flags, Some(&operation), gio::Cancellable::NONE, on_done)
+            }
+            Backing::Unix => {}
+        }
+    }
+
+    /// Free and total bytes, or `None` if the filesystem doesn't report them.
+    pub async fn usage(&self) -> Option<(u64, u64)> {
+        let path = self.path.as_ref()?;
+        let info = gio::File::for_path(path)
+            .query_filesystem_info_future("filesystem::free,filesystem::size", glib::Priority::LOW)
+            .await
+            .ok()?;
+        let total = info.attribute_uint64("filesystem::size");
+        if total == 0 {
+            return None;
+        }
+        Some((info.attribute_uint64("filesystem::free"), total))
+    }
+}
+
+/// Shows why a mount operation failed, unless the user already knows, e.g.
+/// after cancelling the password prompt.
+fn show_failure(window: &gtk::Window, title: &str, error: &glib::Error) {
+    if !error.matches(gio::IOErrorEnum::FailedHandled) {
+        operations::show_error_dialog(window, title, error.message());
+    }
+}
+
+/// Mounted and mountable filesystems, kept up to date through
+/// `gio::VolumeMonitor`. Without GVfs the monitor sees nothing, so mount
+/// points are read from `/proc/self/mountinfo` instead.
+#[derive(Clone)]
+pub struct Devices {
+    monitor: gio::VolumeMonitor,
+    devices: Rc<RefCell<Vec<Device>>>,
+    /// Whether the monitor reported nothing on the last reload, so the list
+    /// came from mountinfo.
+    uses_mountinfo: Rc<Cell<bool>>,
+    handlers: ChangeHandlers,
+}
+
+impl Devices {
+    pub fn new() -> Self {
+        let devices = Devices {
+            monitor: gio::VolumeMonitor::get(),
+            devices: Rc::new(RefCell::new(Vec::new())),
+            uses_mountinfo: Rc::new(Cell::new(false)),
+            handlers: ChangeHandlers::new(),
+        };
+        devices.reload();
+        devices
+    }
+
+    /// Starts following device changes. Needs the main loop, so it's called
+    /// once the application has started.
+    pub fn watch(&self) {
+        let devices = self.clone();
+        self.monitor
+            .connect_mount_added(move |_, _| devices.reload());
+        let devices = self.clone();
+        self.monitor
+            .connect_mount_removed(move |_, _| devices.reload());
+        let devices = self.clone();
+        self.monitor
+            .connect_mount_changed(move |_, _| devices.reload());
+        let devices = self.clone();
+        self.monitor
+            .connect_volume_added(move |_, _| devices.reload());
+        let devices = self.clone();
+        self.monitor
+            .connect_volume_removed(move |_, _| devices.reload());
+        let devices = self.clone();
+        self.monitor
+            .connect_drive_connected(move |_, _| devices.reload());
+        let devices = self.clone();
+        self.monitor
+            .connect_drive_disconnected(move |_, _| devices.reload());
+
+        // mountinfo can't be watched with a file monitor, so poll it
+        let devices = self.clone();
+        glib::timeout_add_seconds_local(MOUNTINFO_POLL_SECONDS, move || {
+            if devices.uses_mountinfo.get() {
+                devices.reload();
+            }
+            glib::ControlFlow::Continue
+        });
+    }
+
+    /// Re-lists the devices, notifying only if the list changed.
+    fn reload(&self) {
+        let mut devices = monitor_devices(&self.monitor);
+        self.uses_mountinfo.set(devices.is_empty());
+        if devices.is_empty() {
+            devices = mountinfo_devices();
+        }
+
+        let unchanged = {
+            let current = self.devices.borrow();
+            current.len() == devices.len()
+                && current
+                    .iter()
+                    .zip(&devices)
+                    .all(|(a, b)| a.name == b.name && a.path == b.path)
+        };
+        *self.devices.borrow_mut() = devices;
+        if !unchanged {
+            self.notify_changed();
+        }
+    }
+
+    pub fn list(&self) -> Vec<Device> {
+        self.devices.borrow().clone()
+    }
+
+    pub fn connect_changed<F>(&self, callback: F) -> usize
+    where
+        F: Fn() + 'static,
+    {
+        self.handlers.connect(move |_| callback())
+    }
+
+    pub fn disconnect(&self, handler_id: usize) {
+        self.handlers.disconnect(handler_id);
+    }
+
+    fn notify_changed(&self) {
+        self.handlers.notify(&());
+    }
+}
+
+impl Default for Devices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drives (with their volumes) first, then volumes without a drive, then
+/// mounts without a volume such as network shares.
+fn monitor_devices(monitor: &gio::VolumeMonitor) -> Vec<Device> {
+    let mut devices = Vec::new();
+
+    for drive in monitor.connected_drives() {
+        let volumes = drive.volumes();
+        if volumes.is_empty() {
+            if drive.has_media() && drive.can_eject() {
+                devices.push(Device {
+                    name: drive.name().to_string(),
+                    icon: Some(drive.icon()),
+                    path: None,
+                    backing: Backing::Drive(drive),
+                });
+            }
+            continue;
+        }
+        devices.extend(volumes.iter().map(volume_device));
+    }
+
+    devices.extend(
+        monitor
+            .volumes()
+            .iter()
+            .filter(|volume| volume.drive().is_none())
+            .map(volume_device),
+    );
+
+    devices.extend(
+        monitor
+            .mounts()
+            .into_iter()
+            .filter(|mount| mount.volume().is_none() && !mount.is_shadowed())
+            .map(mount_device),
+    );
+
+    devices
+}
+
+fn volume_device(volume: &gio::Volume) -> Device {
+    match volume.get_mount() {
+        Some(mount) => mount_device(mount),
+        None => Device {
+            name: volume.name().to_string(),
+            icon: Some(volume.icon()),
+            path: None,
+            backing: Backing::Volume(volume.clone()),
+        },
+    }
+}
+
+fn mount_device(mount: gio::Mount) -> Device {
+    Device {
+        name: mount.name().to_string(),
+        icon: Some(mount.icon()),
+        path: mount.root().path(),
+        backing: Backing::Mount(mount),
+    }
+}
+
+/// Real filesystems from `/proc/self/mountinfo`, leaving out the root (shown
+/// under Places) and system mounts.
+fn mountinfo_devices() -> Vec<Device> {
+    let Ok(contents) = fs::read_to_string("/proc/self/mountinfo") else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter_map(parse_mountinfo_line)
+        .filter(|(mount_point, source)| {
+            source.starts_with("/dev/")
+                && mount_point != "/"
+                && !["/boot", "/snap", "/var/snap", "/proc", "/sys"]
+                    .iter()
+                    .any(|prefix| mount_point.starts_with(prefix))
+        })
+        .map(|(mount_point, _)| {
+            let path = PathBuf::from(&mount_point);
+            Device {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or(mount_point),
+                icon: Some(gio::ThemedIcon::new("drive-harddisk-symbolic").upcast()),
+                path: Some(path),
+                backing: Backing::Unix,
+            }
+        })
+        .collect()
+}
+
+/// Returns the mount point and source of a mountinfo line:
+/// `36 35 98:0 / /mnt/data rw,noatime master:1 - ext4 /dev/sdb1 rw`.
+fn parse_mountinfo_line(line: &str) -> Option<(String, String)> {
+    let fields: Vec<&str> = line.split(' ').collect();
+    let mount_point = fields.get(4)?;
+    let separator = fields.iter().position(|field| *field == "-")?;
+    let source = fields.get(separator + 2)?;
+    Some((unescape(mount_point), unescape(source)))
+}
+
+/// Decodes the octal escapes (`\040` for a space) used in mountinfo.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(digits, 8) {
+                decoded.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mount_point_and_source() {
+        let line = "36 35 98:0 / /mnt/data rw,noatime master:1 - ext4 /dev/sdb1 rw";
+        assert_eq!(
+            parse_mountinfo_line(line),
+            Some(("/mnt/data".to_string(), "/dev/sdb1".to_string()))
+        );
+
+        // any number of optional fields may come before the separator
+        let line = "36 35 98:0 / /media/usb rw shared:2 master:1 - vfat /dev/sdc1 rw";
+        assert_eq!(
+            parse_mountinfo_line(line),
+            Some(("/media/usb".to_string(), "/dev/sdc1".to_string()))
+        );
+        let line = "22 1 0:21 / /proc rw - proc proc rw";
+        assert_eq!(
+            parse_mountinfo_line(line),
+            Some(("/proc".to_string(), "proc".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_lines() {
+        assert_eq!(parse_mountinfo_line(""), None);
+        assert_eq!(parse_mountinfo_line("36 35 98:0 / /mnt/data rw"), None);
+        assert_eq!(
+            parse_mountinfo_line("36 35 98:0 / /mnt/data rw - ext4"),
+            None
+        );
+    }
+
+    #[test]
+    fn unescapes_octal_sequences() {
+        let line = r"36 35 98:0 / /mnt/My\040Disk rw - ext4 /dev/sdb1 rw";
+        assert_eq!(
+            parse_mountinfo_line(line),
+            Some(("/mnt/My Disk".to_string(), "/dev/sdb1".to_string()))
+        );
+        assert_eq!(unescape(r"tab\011and\012newline"), "tab\tand\nnewline");
+        assert_eq!(unescape(r"back\134slash"), "back\\slash");
+        assert_eq!(unescape(r"\303\251t\303\251"), "été");
+    }
+
+    #[test]
+    fn keeps_invalid_escapes() {
+        assert_eq!(unescape(r"a\04"), r"a\04");
+        assert_eq!(unescape(r"a\9zz"), r"a\9zz");
+        assert_eq!(unescape("trailing\\"), "trailing\\");
+        assert_eq!(unescape(r"\777"), r"\777");
+    }
+}
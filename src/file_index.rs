@@ -0,0 +1,541 @@
+use crate::file_info::{self, FileInfo};
+use crate::fuzzy;
+use crate::query::Query;
+use crate::recursive_search::{self, walk, GitignoreFilter, SearchHandle, WalkOptions};
+use crate::search::{glob_to_regex, NameMatcher};
+use crate::settings::{self, Settings, SettingsStore};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Most results returned for one indexed search.
+const MAX_RESULTS: usize = 2000;
+/// How often changes picked up from the file system are written to disk.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+fn database_path() -> PathBuf {
+    settings::data_dir().join("file-index.json")
+}
+
+/// What the index knows about one file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    is_dir: bool,
+    size: u64,
+    /// Seconds since the Unix epoch.
+    modified: u64,
+}
+
+impl IndexEntry {
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        IndexEntry {
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    /// Describes the entry without touching the disk; the MIME type comes
+    /// from the name.
+    fn to_file_info(&self, path: &Path, name: &str) -> FileInfo {
+        let (mime_type, needs_sniffing) = if self.is_dir {
+            ("inode/directory".to_string(), false)
+        } else {
+            file_info::guess_mime_type_from_name(path)
+        };
+        FileInfo {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+            is_dir: self.is_dir,
+            size: self.size,
+            allocated_size: None,
+            modified: UNIX_EPOCH + Duration::from_secs(self.modified),
+            is_hidden: name.starts_with('.'),
+            mime_type,
+            needs_sniffing,
+            link_target: None,
+            is_dangling: false,
+            unix: None,
+            item_count: None,
+            folder_size: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Database {
+    /// The configuration the entries were collected with; a saved index is
+    /// only reused for the same one.
+    config: IndexConfig,
+    entries: HashMap<PathBuf, IndexEntry>,
+}
+
+/// Which folders are indexed, from the preferences.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct IndexConfig {
+    roots: Vec<PathBuf>,
+    exclusions: Vec<String>,
+}
+
+/// Names or paths that are left out of the index.
+struct Exclusions {
+    names: Vec<Regex>,
+    paths: Vec<PathBuf>,
+}
+
+impl Exclusions {
+    /// Patterns starting with `/` or `~/` exclude a folder; anything else is a
+    /// glob matched against file names, such as `node_modules` or `*.o`.
+    fn new(patterns: &[String]) -> Self {
+        let mut names = Vec::new();
+        let mut paths = Vec::new();
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            if let Some(relative) = pattern.strip_prefix("~/") {
+                if let Some(home) = dirs::home_dir() {
+                    paths.push(home.join(relative));
+                }
+            } else if pattern.starts_with('/') {
+                paths.push(PathBuf::from(pattern));
+            } else {
+                match RegexBuilder::new(&glob_to_regex(pattern))
+                    .case_insensitive(true)
+                    .build()
+                {
+                    Ok(regex) => names.push(regex),
+                    Err(e) => eprintln!("Invalid index exclusion {}: {}", pattern, e),
+                }
+            }
+        }
+        Exclusions { names, paths }
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.names.iter().any(|regex| regex.is_match(&name))
+            || self.paths.iter().any(|excluded| path.starts_with(excluded))
+    }
+}
+
+/// State shared with the scanning and watching threads.
+struct Shared {
+    entries: RwLock<HashMap<PathBuf, IndexEntry>>,
+    ready: AtomicBool,
+    /// Bumped whenever the configuration changes, so that threads working
+    /// for an older configuration stop.
+    generation: AtomicU64,
+    /// Paths changed while a rescan runs, to be applied again on top of its
+    /// result. `None` when no rescan is running.
+    rescan_changes: Mutex<Option<Vec<(PathBuf, bool)>>>,
+    /// Whether the entries changed since they were last saved.
+    dirty: AtomicBool,
+}
+
+/// An optional index of file names below the roots chosen in the preferences,
+/// kept current with file system notifications. Recursive name searches under
+/// an indexed root are answered from it without scanning the disk.
+#[derive(Clone)]
+pub struct FileIndex {
+    shared: Arc<Shared>,
+    config: Rc<RefCell<Option<IndexConfig>>>,
+    watcher: Rc<RefCell<Option<RecommendedWatcher>>>,
+}
+
+impl FileIndex {
+    pub fn new() -> Self {
+        FileIndex {
+            shared: Arc::new(Shared {
+                entries: RwLock::new(HashMap::new()),
+                ready: AtomicBool::new(false),
+                generation: AtomicU64::new(0),
+                rescan_changes: Mutex::new(None),
+                dirty: AtomicBool::new(false),
+            }),
+            config: Rc::new(RefCell::new(None)),
+            watcher: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Follows the index preferences, now and whenever they change.
+    pub fn bind_settings(&self, settings: &SettingsStore) {
+        self.apply_settings(&settings.get());
+        let file_index = self.clone();
+        settings.connect_changed(move |settings| file_index.apply_settings(settings));
+    }
+
+    fn apply_settings(&self, settings: &Settings) {
+        let config = settings.index_enabled.then(|| IndexConfig {
+            roots: settings.indexed_roots.clone(),
+            exclusions: settings.index_exclusions.clone(),
+        });
+        if *self.config.borrow() == config {
+            return;
+        }
+        *self.config.borrow_mut() = config.clone();
+
+        let generation = self.shared.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.shared.ready.store(false, Ordering::SeqCst);
+        self.shared.entries.write().unwrap().clear();
+        *self.watcher.borrow_mut() = None;
+        *self.shared.rescan_changes.lock().unwrap() = config.as_ref().map(|_| Vec::new());
+
+        let Some(config) = config else {
+            // Don't leave a copy of the file list behind once indexing is off
+            let _ = fs::remove_file(database_path());
+            return;
+        };
+
+        let exclusions = Arc::new(Exclusions::new(&config.exclusions));
+        self.start_watching(&config.roots, generation, exclusions.clone());
+
+        let shared = self.shared.clone();
+        let saved_config = config.clone();
+        thread::spawn(move || build_index(shared, config, exclusions, generation));
+        let shared = self.shared.clone();
+        thread::spawn(move || save_periodically(shared, saved_config, generation));
+    }
+
+    fn start_watching(&self, roots: &[PathBuf], generation: u64, exclusions: Arc<Exclusions>) {
+        let shared = self.shared.clone();
+        let watched_roots = roots.to_vec();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("File index watcher error: {}", e);
+                    return;
+                }
+            };
+            if shared.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+
+            // Folders that appear, or are renamed into place, are scanned whole
+            let descend = matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+            );
+            for path in &event.paths {
+                if is_hidden_below_root(path, &watched_roots) {
+                    continue;
+                }
+                if let Some(changes) = shared.rescan_changes.lock().unwrap().as_mut() {
+                    changes.push((path.clone(), descend));
+                }
+                update_path(&shared, path, &exclusions, descend, generation);
+            }
+        });
+
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Failed to watch indexed folders: {}", e);
+                return;
+            }
+        };
+        for root in roots {
+            if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+                eprintln!("Failed to watch {}: {}", root.display(), e);
+            }
+        }
+        *self.watcher.borrow_mut() = Some(watcher);
+    }
+
+    /// Whether searches below `root` can be answered from the index.
+    pub fn covers(&self, root: &Path) -> bool {
+        self.shared.ready.load(Ordering::SeqCst)
+            && self
+                .config
+                .borrow()
+                .as_ref()
+                .is_some_and(|config| config.roots.iter().any(|r| root.starts_with(r)))
+    }
+
+    /// Whether a search below `root` with `options` can be answered from
+    /// the index: not when `root` is outside it, or when `options` reach
+    /// files the index leaves out (hidden files, linked folders, other file
+    /// systems).
+    pub fn can_search(&self, root: &Path, options: WalkOptions) -> bool {
+        let indexed = options.skip_hidden && !options.follow_symlinks && options.same_filesystem;
+        indexed && self.covers(root)
+    }
+
+    /// Finds indexed files below `root` whose names match `matcher` and that
+    /// pass the filters of `query`, on a worker thread like
+    /// `recursive_search::spawn_search`. Plain text is matched fuzzily;
+    /// patterns rank shorter names first. Results arrive best first, at most
+    /// `MAX_RESULTS` of them. Check `can_search` first.
+    pub fn search<R, D>(
+        &self,
+        root: PathBuf,
+        matcher: NameMatcher,
+        query: Query,
+        options: WalkOptions,
+        on_results: R,
+        on_finished: D,
+    ) -> SearchHandle
+    where
+        R: Fn(Vec<FileInfo>) + 'static,
+        D: FnOnce() + 'static,
+    {
+        let shared = self.shared.clone();
+        recursive_search::spawn_worker(
+            move |mut results| {
+                // Names are ranked first; the filters may touch the disk, so
+                // they only run until enough results passed them
+                let mut ranked: Vec<(i64, PathBuf, IndexEntry)> = {
+                    let entries = shared.entries.read().unwrap();
+                    entries
+                        .iter()
+                        .filter(|(path, _)| path.starts_with(&root) && **path != root)
+                        .filter_map(|(path, entry)| {
+                            let name = path.file_name()?.to_string_lossy();
+                            let score = match &matcher {
+                                NameMatcher::Substring(text) => fuzzy::score(text, &name)?,
+                                NameMatcher::Regex(regex) => {
+                                    if !regex.is_match(&name) {
+                                        return None;
+                                    }
+                                    -(name.chars().count() as i64)
+                                }
+                            };
+                            Some((score, path.clone(), entry.clone()))
+                        })
+                        .collect()
+                };
+                ranked.sort_by(|(a_score, a, _), (b_score, b, _)| {
+                    b_score
+                        .cmp(a_score)
+                        .then_with(|| a.as_os_str().len().cmp(&b.as_os_str().len()))
+                });
+
+                let mut gitignore = options
+                    .respect_gitignore
+                    .then(|| GitignoreFilter::new(&root));
+                let mut found = 0;
+                for (_, path, entry) in ranked {
+                    if found == MAX_RESULTS || results.is_cancelled() {
+                        break;
+                    }
+                    if let Some(gitignore) = gitignore.as_mut() {
+                        if gitignore.is_ignored(&path, entry.is_dir) {
+                            continue;
+                        }
+                    }
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    let mut file_info = entry.to_file_info(&path, &name);
+                    // Only `type:` filters need to look inside; the view
+                    // reads the other types in the background
+                    if query.needs_mime_type() {
+                        file_info.sniff();
+                    }
+                    if query.matches(&file_info) {
+                        results.push(file_info);
+                        found += 1;
+                    }
+                }
+            },
+            on_results,
+            on_finished,
+        )
+    }
+
+    /// Indexed folders whose names fuzzily match `pattern`, best first.
+    pub fn matching_directories(&self, pattern: &str, limit: usize) -> Vec<PathBuf> {
+        if pattern.is_empty() || !self.shared.ready.load(Ordering::SeqCst) {
+            return Vec::new();
+        }
+
+        let entries = self.shared.entries.read().unwrap();
+        let mut directories: Vec<(i64, &PathBuf)> = entries
+            .iter()
+            .filter(|(_, entry)| entry.is_dir)
+            .filter_map(|(path, _)| {
+                let name = path.file_name()?.to_string_lossy();
+                fuzzy::score(pattern, &name).map(|score| (score, path))
+            })
+            .collect();
+        directories.sort_by(|a, b| b.0.cmp(&a.0));
+        directories
+            .into_iter()
+            .take(limit)
+            .map(|(_, path)| path.clone())
+            .collect()
+    }
+}
+
+impl Default for FileIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Loads the saved index so searches work right away, then rescans the roots
+/// to catch up with changes made while zfile was not running.
+fn build_index(
+    shared: Arc<Shared>,
+    config: IndexConfig,
+    exclusions: Arc<Exclusions>,
+    generation: u64,
+) {
+    let is_current = || shared.generation.load(Ordering::SeqCst) == generation;
+
+    if let Some(database) = settings::load_json::<Database>(&database_path(), "file index") {
+        if database.config == config && is_current() {
+            *shared.entries.write().unwrap() = database.entries;
+            shared.ready.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let cancelled = AtomicBool::new(false);
+    let mut entries = HashMap::new();
+    for root in &config.roots {
+        scan(root, &exclusions, &cancelled, &mut entries, || {
+            !is_current()
+        });
+    }
+
+    if !is_current() {
+        return;
+    }
+
+    // Changes seen during the scan may have been missed by it. Holding the
+    // queue while swapping means later changes go straight to the new entries.
+    let changes = {
+        let mut rescan_changes = shared.rescan_changes.lock().unwrap();
+        *shared.entries.write().unwrap() = entries;
+        rescan_changes.take().unwrap_or_default()
+    };
+    for (path, descend) in changes {
+        update_path(&shared, &path, &exclusions, descend, generation);
+    }
+    shared.ready.store(true, Ordering::SeqCst);
+    save(&shared, &config);
+}
+
+/// Adds every file below `root` that is not excluded to `entries`.
+fn scan<F>(
+    root: &Path,
+    exclusions: &Exclusions,
+    cancelled: &AtomicBool,
+    entries: &mut HashMap<PathBuf, IndexEntry>,
+    is_stale: F,
+) where
+    F: Fn() -> bool,
+{
+    let options = WalkOptions {
+        respect_gitignore: false,
+        ..WalkOptions::default()
+    };
+    walk(root, options, cancelled, |path, _| {
+        if is_stale() {
+            cancelled.store(true, Ordering::Relaxed);
+            return false;
+        }
+        if exclusions.is_excluded(path) {
+            return false;
+        }
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            entries.insert(path.to_path_buf(), IndexEntry::from_metadata(&metadata));
+        }
+        true
+    });
+}
+
+/// Brings the entry for `path` in line with the file system after a change
+/// notification. New folders (such as ones moved in) are scanned when
+/// `descend` is set.
+fn update_path(
+    shared: &Shared,
+    path: &Path,
+    exclusions: &Exclusions,
+    descend: bool,
+    generation: u64,
+) {
+    if path
+        .ancestors()
+        .any(|ancestor| exclusions.is_excluded(ancestor))
+    {
+        return;
+    }
+
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => {
+            let mut found = HashMap::new();
+            found.insert(path.to_path_buf(), IndexEntry::from_metadata(&metadata));
+            if descend && metadata.is_dir() {
+                let cancelled = AtomicBool::new(false);
+                scan(path, exclusions, &cancelled, &mut found, || {
+                    shared.generation.load(Ordering::SeqCst) != generation
+                });
+            }
+            shared.entries.write().unwrap().extend(found);
+        }
+        Err(_) => {
+            shared
+                .entries
+                .write()
+                .unwrap()
+                .retain(|indexed, _| !indexed.starts_with(path));
+        }
+    }
+    shared.dirty.store(true, Ordering::SeqCst);
+}
+
+/// Whether `path` is inside a hidden folder (or is hidden itself) below the
+/// indexed root containing it; the scan skips those, so notifications must too.
+fn is_hidden_below_root(path: &Path, roots: &[PathBuf]) -> bool {
+    let Some(relative) = roots.iter().find_map(|root| path.strip_prefix(root).ok()) else {
+        return true;
+    };
+    relative
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// Writes changes picked up from the file system to disk every
+/// `SAVE_INTERVAL`, until the configuration changes. Nothing is written while
+/// the index is unchanged or still being built.
+fn save_periodically(shared: Arc<Shared>, config: IndexConfig, generation: u64) {
+    loop {
+        thread::sleep(SAVE_INTERVAL);
+        if shared.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        if shared.ready.load(Ordering::SeqCst) && shared.dirty.load(Ordering::SeqCst) {
+            save(&shared, &config);
+        }
+    }
+}
+
+fn save(shared: &Shared, config: &IndexConfig) {
+    shared.dirty.store(false, Ordering::SeqCst);
+
+    let entries = shared.entries.read().unwrap();
+    let database = DatabaseRef {
+        config,
+        entries: &entries,
+    };
+    settings::save_json(&database_path(), &database, "file index");
+}
+
+/// Borrowed form of [`Database`] for saving without copying the entries.
+#[derive(Serialize)]
+struct DatabaseRef<'a> {
+    config: &'a IndexConfig,
+    entries: &'a HashMap<PathBuf, IndexEntry>,
+}
@@ -1,22 +1,145 @@
+use crate::folder_size::FolderSize;
+use crate::users;
+use byte_unit::{Byte, Unit};
 use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
+use std::io::Read;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Clone, Debug)]
 pub struct FileInfo {
     pub path: PathBuf,
     pub name: String,
     pub is_dir: bool,
+    /// Apparent size: the length of the contents.
     pub size: u64,
+    /// Space taken on disk, which is smaller for sparse or compressed files
+    /// and rounded up to whole blocks. `None` for index entries.
+    pub allocated_size: Option<u64>,
     pub modified: SystemTime,
-    #[allow(unused)]
     pub is_hidden: bool,
+    /// E.g. `text/x-rust`, or `inode/directory` for folders.
+    pub mime_type: String,
+    /// The MIME type was guessed from the name alone, and the first bytes of
+    /// the file may tell more; see [`FileInfo::sniff`].
+    pub needs_sniffing: bool,
+    /// Where a symbolic link points, as stored in the link. The other fields
+    /// describe the target, unless the link is dangling.
+    pub link_target: Option<PathBuf>,
+    /// A symbolic link whose target doesn't exist.
+    pub is_dangling: bool,
+    /// Ownership, permissions and the other POSIX fields. `None` for entries
+    /// from the file index, which doesn't store them.
+    pub unix: Option<UnixMetadata>,
+    /// Entries directly inside a folder, counted in the background once its
+    /// parent is listed.
+    pub item_count: Option<u64>,
+    /// Total size of everything inside a folder, once measured.
+    pub folder_size: Option<FolderSize>,
+}
+
+/// Whether sizes are written in powers of 1000 (KB, MB) or 1024 (KiB, MiB).
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    #[default]
+    Decimal,
+    Binary,
+}
+
+/// How sizes are shown, from the preferences.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SizeFormat {
+    pub units: SizeUnits,
+    /// Show the space allocated on disk instead of the apparent size.
+    pub allocated: bool,
+}
+
+/// POSIX metadata of a file (of the link itself for symbolic links).
+#[derive(Clone, Debug)]
+pub struct UnixMetadata {
+    pub uid: u32,
+    pub gid: u32,
+    /// Permission bits, including setuid, setgid and sticky.
+    pub mode: u32,
+    pub inode: u64,
+    pub hard_links: u64,
+    pub device: u64,
+    pub accessed: SystemTime,
+    /// Birth time, when the filesystem records it.
+    pub created: Option<SystemTime>,
+}
+
+impl UnixMetadata {
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        UnixMetadata {
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            mode: metadata.mode() & 0o7777,
+            inode: metadata.ino(),
+            hard_links: metadata.nlink(),
+            device: metadata.dev(),
+            accessed: metadata.accessed().unwrap_or(UNIX_EPOCH),
+            created: metadata.created().ok(),
+        }
+    }
+
+    pub fn owner_name(&self) -> String {
+        users::user_name(self.uid)
+    }
+
+    pub fn group_name(&self) -> String {
+        users::group_name(self.gid)
+    }
+
+    /// `rwxr-xr-x`, with `s`/`t` for the setuid, setgid and sticky bits.
+    pub fn format_permissions(&self) -> String {
+        let special = [(0o4000, 's'), (0o2000, 's'), (0o1000, 't')];
+        let mut text = String::with_capacity(9);
+        for (class, (special_bit, special_char)) in special.iter().enumerate() {
+            let bits = self.mode >> (6 - 3 * class);
+            text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            let execute = bits & 0o1 != 0;
+            text.push(match (self.mode & special_bit != 0, execute) {
+                (true, true) => *special_char,
+                (true, false) => special_char.to_ascii_uppercase(),
+                (false, true) => 'x',
+                (false, false) => '-',
+            });
+        }
+        text
+    }
+
+    /// Octal permissions, e.g. `0755`.
+    pub fn format_mode(&self) -> String {
+        format!("{:04o}", self.mode)
+    }
 }
 
 impl FileInfo {
+    /// Describes `path` without dropping broken links: a symbolic link is
+    /// described by its target when it exists, and by the link otherwise.
+    /// The file isn't read; its type comes from its name.
     pub fn from_path(path: &Path) -> std::io::Result<Self> {
-        let metadata = fs::metadata(path)?;
+        let link_metadata = fs::symlink_metadata(path)?;
+        let link_target = if link_metadata.file_type().is_symlink() {
+            Some(fs::read_link(path)?)
+        } else {
+            None
+        };
+        let unix = UnixMetadata::from_metadata(&link_metadata);
+        let (metadata, is_dangling) = match &link_target {
+            Some(_) => match fs::metadata(path) {
+                Ok(metadata) => (metadata, false),
+                Err(_) => (link_metadata, true),
+            },
+            None => (link_metadata, false),
+        };
         let name = path
             .file_name()
             .and_then(|n| n.to_str())
@@ -24,66 +147,337 @@ impl FileInfo {
             .to_string();
 
         let is_hidden = name.starts_with('.');
+        let is_dir = metadata.is_dir();
+        let (mime_type, needs_sniffing) = guess_mime_type(path, metadata.file_type());
 
         Ok(FileInfo {
             path: path.to_path_buf(),
             name,
-            is_dir: metadata.is_dir(),
+            is_dir,
             size: metadata.len(),
+            allocated_size: Some(metadata.blocks() * 512),
             modified: metadata.modified().unwrap_or(SystemTime::now()),
             is_hidden,
+            mime_type,
+            needs_sniffing,
+            link_target,
+            is_dangling,
+            unix: Some(unix),
+            item_count: None,
+            folder_size: None,
         })
     }
 
-    pub fn format_size(&self) -> String {
-        if self.is_dir {
-            return "Folder".to_string();
+    /// Settles a MIME type guessed from the name by reading the first bytes
+    /// of the file. This blocks on the disk, so keep it off the main thread
+    /// when describing many files.
+    pub fn sniff(&mut self) {
+        if !self.needs_sniffing {
+            return;
         }
+        if let Some(mime_type) = sniff_mime_type(&self.path) {
+            self.mime_type = mime_type;
+        }
+        self.needs_sniffing = false;
+    }
 
-        let size = self.size as f64;
+    pub fn is_symlink(&self) -> bool {
+        self.link_target.is_some()
+    }
 
-        if size < 1024.0 {
-            format!("{} B", size)
-        } else if size < 1024.0 * 1024.0 {
-            format!("{:.1} KB", size / 1024.0)
-        } else if size < 1024.0 * 1024.0 * 1024.0 {
-            format!("{:.1} MB", size / (1024.0 * 1024.0))
-        } else {
-            format!("{:.1} GB", size / (1024.0 * 1024.0 * 1024.0))
+    /// The link target resolved against the link's folder.
+    pub fn resolved_link_target(&self) -> Option<PathBuf> {
+        let target = self.link_target.as_ref()?;
+        let parent = self.path.parent().unwrap_or(Path::new("/"));
+        Some(parent.join(target))
+    }
+
+    /// Tooltip text: the path, the target for links and the exact size.
+    pub fn tooltip(&self) -> String {
+        let mut text = self.path.to_string_lossy().to_string();
+        match &self.link_target {
+            Some(target) if self.is_dangling => {
+                text.push_str(&format!("\nBroken link to {}", target.display()));
+            }
+            Some(target) => text.push_str(&format!("\nLink to {}", target.display())),
+            None => {}
+        }
+
+        let sizes = match (self.is_dir, self.folder_size) {
+            (false, _) => Some((self.size, self.allocated_size)),
+            (true, Some(folder_size)) => Some((folder_size.bytes, Some(folder_size.allocated))),
+            (true, None) => None,
+        };
+        if let Some((size, allocated_size)) = sizes {
+            text.push('\n');
+            text.push_str(&format_byte_count(size));
+            if let Some(allocated_size) = allocated_size {
+                text.push_str(&format!(", {} on disk", format_byte_count(allocated_size)));
+            }
+        }
+        text
+    }
+
+    /// Whether the file looks like an editor or tool backup (`name~`, `name.bak`).
+    pub fn is_backup(&self) -> bool {
+        !self.is_dir && (self.name.ends_with('~') || self.name.ends_with(".bak"))
+    }
+
+    /// The apparent or allocated size of a file, or of everything in a
+    /// measured folder. Unmeasured folders have none.
+    pub fn display_size(&self, format: SizeFormat) -> Option<u64> {
+        match (self.is_dir, self.folder_size) {
+            (false, _) if format.allocated => Some(self.allocated_size.unwrap_or(self.size)),
+            (false, _) => Some(self.size),
+            (true, Some(folder_size)) if format.allocated => Some(folder_size.allocated),
+            (true, Some(folder_size)) => Some(folder_size.bytes),
+            (true, None) => None,
+        }
+    }
+
+    /// The size of a file; for a folder its measured size, else its number
+    /// of items when counted.
+    pub fn format_size(&self, format: SizeFormat) -> String {
+        if let Some(size) = self.display_size(format) {
+            return format_bytes(size, format.units);
+        }
+        match self.item_count {
+            Some(1) => "1 item".to_string(),
+            Some(count) => format!("{} items", count),
+            None => "Folder".to_string(),
         }
     }
 
     pub fn format_modified(&self) -> String {
-        let datetime: DateTime<Local> = self.modified.into();
-        datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+        format_time(self.modified)
     }
 
-    pub fn icon_name(&self) -> &'static str {
-        if self.is_dir {
-            "folder-symbolic"
-        } else {
-            // Simple icon selection based on extension
-            if let Some(ext) = self.path.extension().and_then(|e| e.to_str()) {
-                match ext.to_lowercase().as_str() {
-                    "txt" | "md" | "rst" => "text-x-generic-symbolic",
-                    "jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg" | "webp" => {
-                        "image-x-generic-symbolic"
-                    }
-                    "mp3" | "wav" | "ogg" | "flac" | "m4a" => "audio-x-generic-symbolic",
-                    "mp4" | "avi" | "mkv" | "mov" | "webm" => "video-x-generic-symbolic",
-                    "pdf" => "application-pdf-symbolic",
-                    "zip" | "tar" | "gz" | "bz2" | "xz" | "7z" | "rar" => {
-                        "package-x-generic-symbolic"
-                    }
-                    "rs" | "py" | "js" | "c" | "cpp" | "h" | "java" | "go" => {
-                        "text-x-script-symbolic"
-                    }
-                    "html" | "htm" | "xml" | "css" => "text-html-symbolic",
-                    _ => "text-x-generic-symbolic",
-                }
-            } else {
-                "text-x-generic-symbolic"
-            }
+    /// Whether the file is an image small enough to be shown as its own thumbnail.
+    pub fn has_thumbnail(&self) -> bool {
+        const MAX_THUMBNAIL_SOURCE_SIZE: u64 = 10 * 1024 * 1024;
+
+        !self.is_dir
+            && self.size <= MAX_THUMBNAIL_SOURCE_SIZE
+            && matches!(
+                self.mime_type.as_str(),
+                "image/png"
+                    | "image/jpeg"
+                    | "image/gif"
+                    | "image/bmp"
+                    | "image/webp"
+                    | "image/svg+xml"
+            )
+    }
+
+    /// Themed icon for the file's content type.
+    pub fn icon(&self) -> gio::Icon {
+        gio::content_type_get_icon(&self.mime_type)
+    }
+
+    /// Human-readable type, e.g. "Rust source code" or "PNG image".
+    pub fn type_description(&self) -> String {
+        gio::content_type_get_description(&self.mime_type).to_string()
+    }
+}
+
+const DECIMAL_UNITS: [Unit; 7] = [
+    Unit::B,
+    Unit::KB,
+    Unit::MB,
+    Unit::GB,
+    Unit::TB,
+    Unit::PB,
+    Unit::EB,
+];
+const BINARY_UNITS: [Unit; 7] = [
+    Unit::B,
+    Unit::KiB,
+    Unit::MiB,
+    Unit::GiB,
+    Unit::TiB,
+    Unit::PiB,
+    Unit::EiB,
+];
+
+/// A size with one decimal in the largest unit that keeps it below 1000
+/// (or 1024) once rounded, e.g. "999 B", "1.5 KB", "1 MB" for 999,950 bytes.
+pub fn format_bytes(bytes: u64, units: SizeUnits) -> String {
+    let (ladder, step) = match units {
+        SizeUnits::Decimal => (&DECIMAL_UNITS, 1000.0),
+        SizeUnits::Binary => (&BINARY_UNITS, 1024.0),
+    };
+
+    let byte = Byte::from_u64(bytes);
+    let mut adjusted = byte.get_adjusted_unit(Unit::B);
+    for unit in ladder {
+        adjusted = byte.get_adjusted_unit(*unit);
+        if (adjusted.get_value() * 10.0).round() < step * 10.0 {
+            break;
+        }
+    }
+    // `#` drops a zero fraction: "1 KB" rather than "1.0 KB"
+    format!("{:#.1}", adjusted)
+}
+
+/// The exact size with thousands separators, e.g. "1,234,567 bytes".
+pub fn format_byte_count(bytes: u64) -> String {
+    let digits = bytes.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
         }
+        grouped.push(digit);
+    }
+    if bytes == 1 {
+        "1 byte".to_string()
+    } else {
+        format!("{} bytes", grouped)
+    }
+}
+
+pub fn format_time(time: SystemTime) -> String {
+    let datetime: DateTime<Local> = time.into();
+    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Bytes read from the start of a file when its name doesn't settle the type.
+const SNIFF_LENGTH: usize = 4096;
+
+/// The MIME type of `path` from its file type and name, without reading it.
+/// Also returns whether the name is inconclusive (no or unknown extension),
+/// in which case [`sniff_mime_type`] may do better.
+pub fn guess_mime_type(path: &Path, file_type: fs::FileType) -> (String, bool) {
+    let special = if file_type.is_dir() {
+        "inode/directory"
+    } else if file_type.is_symlink() {
+        "inode/symlink"
+    } else if file_type.is_fifo() {
+        "inode/fifo"
+    } else if file_type.is_socket() {
+        "inode/socket"
+    } else if file_type.is_block_device() {
+        "inode/blockdevice"
+    } else if file_type.is_char_device() {
+        "inode/chardevice"
+    } else {
+        return guess_mime_type_from_name(path);
+    };
+    (special.to_string(), false)
+}
+
+/// The MIME type of a regular file from its name alone, and whether the
+/// name is inconclusive.
+pub fn guess_mime_type_from_name(path: &Path) -> (String, bool) {
+    let (content_type, uncertain) = gio::content_type_guess(Some(path), &[]);
+    (mime_type_for(&content_type), uncertain)
+}
+
+/// The MIME type of `path` from its name and first bytes. `None` unless it
+/// is a readable regular file: opening a FIFO or a device can block for good.
+pub fn sniff_mime_type(path: &Path) -> Option<String> {
+    if !fs::metadata(path).ok()?.is_file() {
+        return None;
+    }
+    let mut head = Vec::with_capacity(SNIFF_LENGTH);
+    fs::File::open(path)
+        .ok()?
+        .take(SNIFF_LENGTH as u64)
+        .read_to_end(&mut head)
+        .ok()?;
+    let (content_type, _) = gio::content_type_guess(Some(path), &head);
+    Some(mime_type_for(&content_type))
+}
+
+/// Content types are MIME types on Linux; elsewhere they are converted.
+fn mime_type_for(content_type: &str) -> String {
+    gio::content_type_get_mime_type(content_type)
+        .map(|mime_type| mime_type.to_string())
+        .unwrap_or_else(|| content_type.to_string())
+}
+
+/// Reads the names listed in a directory's `.hidden` file, one per line, as
+/// Nautilus does.
+pub fn read_hidden_names(dir: &Path) -> HashSet<String> {
+    fs::read_to_string(dir.join(".hidden"))
+        .map(|contents| {
+            contents
+                .lines()
+                .map(|line| line.trim_end_matches('\r'))
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(bytes: u64) -> String {
+        format_bytes(bytes, SizeUnits::Decimal)
+    }
+
+    fn binary(bytes: u64) -> String {
+        format_bytes(bytes, SizeUnits::Binary)
+    }
+
+    #[test]
+    fn formats_bytes_below_the_first_step() {
+        assert_eq!(decimal(0), "0 B");
+        assert_eq!(decimal(1), "1 B");
+        assert_eq!(decimal(999), "999 B");
+        assert_eq!(binary(0), "0 B");
+        assert_eq!(binary(999), "999 B");
+        assert_eq!(binary(1000), "1000 B");
+        assert_eq!(binary(1023), "1023 B");
+    }
+
+    #[test]
+    fn switches_units_at_1000_or_1024() {
+        assert_eq!(decimal(1000), "1 KB");
+        assert_eq!(decimal(1023), "1 KB");
+        assert_eq!(decimal(1024), "1 KB");
+        assert_eq!(binary(1024), "1 KiB");
+    }
+
+    #[test]
+    fn drops_a_zero_fraction_and_keeps_one_decimal() {
+        assert_eq!(decimal(1536), "1.5 KB");
+        assert_eq!(binary(1536), "1.5 KiB");
+        assert_eq!(decimal(1_000_000), "1 MB");
+        assert_eq!(binary(1_000_000), "976.6 KiB");
+    }
+
+    #[test]
+    fn moves_up_a_unit_when_rounding_reaches_the_step() {
+        assert_eq!(decimal(999_949), "999.9 KB");
+        assert_eq!(decimal(999_950), "1 MB");
+        assert_eq!(binary(999_950), "976.5 KiB");
+        assert_eq!(binary(1_048_524), "1023.9 KiB");
+    }
+
+    #[test]
+    fn formats_large_sizes() {
+        assert_eq!(decimal(1 << 40), "1.1 TB");
+        assert_eq!(binary(1 << 40), "1 TiB");
+        assert_eq!(decimal(3_500_000_000_000_000), "3.5 PB");
+        assert_eq!(binary(3_500_000_000_000_000), "3.1 PiB");
+        assert_eq!(decimal(u64::MAX), "18.4 EB");
+        assert_eq!(binary(u64::MAX), "16 EiB");
+    }
+
+    #[test]
+    fn formats_exact_byte_counts() {
+        assert_eq!(format_byte_count(0), "0 bytes");
+        assert_eq!(format_byte_count(1), "1 byte");
+        assert_eq!(format_byte_count(999), "999 bytes");
+        assert_eq!(format_byte_count(1000), "1,000 bytes");
+        assert_eq!(format_byte_count(1_234_567), "1,234,567 bytes");
+        assert_eq!(
+            format_byte_count(u64::MAX),
+            "18,446,744,073,709,551,615 bytes"
+        );
     }
 }
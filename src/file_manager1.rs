@@ -0,0 +1,196 @@
+use crate::services::AppServices;
+use gtk::prelude::*;
+use gtk::Application;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const BUS_NAME: &str = "org.freedesktop.FileManager1";
+const OBJECT_PATH: &str = "/org/freedesktop/FileManager1";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.freedesktop.FileManager1">
+    <method name="ShowFolders">
+      <arg type="as" name="URIs" direction="in"/>
+      <arg type="s" name="StartupId" direction="in"/>
+    </method>
+    <method name="ShowItems">
+      <arg type="as" name="URIs" direction="in"/>
+      <arg type="s" name="StartupId" direction="in"/>
+    </method>
+    <method name="ShowItemProperties">
+      <arg type="as" name="URIs" direction="in"/>
+      <arg type="s" name="StartupId" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// Owns `org.freedesktop.FileManager1` on the session bus so other applications
+/// can ask zfile to reveal files ("Show in folder").
+pub fn register(app: &Application, services: &AppServices) -> gio::OwnerId {
+    let app_weak = app.downgrade();
+    let services = services.clone();
+    gio::bus_own_name(
+        gio::BusType::Session,
+        BUS_NAME,
+        gio::BusNameOwnerFlags::NONE,
+        move |connection, _| {
+            if let Some(app) = app_weak.upgrade() {
+                if let Err(e) = register_object(&connection, &app, &services) {
+                    eprintln!("Failed to export {}: {}", BUS_NAME, e);
+                }
+            }
+        },
+        |_, _| {},
+        |_, name| {
+            eprintln!("Lost D-Bus name {}, another file manager owns it", name);
+        },
+    )
+}
+
+fn register_object(
+    connection: &gio::DBusConnection,
+    app: &Application,
+    services: &AppServices,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface_info = node_info
+        .lookup_interface(BUS_NAME)
+        .expect("interface is defined in INTERFACE_XML");
+
+    let app_weak = app.downgrade();
+    let services = services.clone();
+    connection
+        .register_object(OBJECT_PATH, &interface_info)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            let Some(app) = app_weak.upgrade() else {
+                invocation.return_value(None);
+                return;
+            };
+
+            let Some((uris, startup_id)) = parameters.get::<(Vec<String>, String)>() else {
+                invocation.return_error(
+                    gio::IOErrorEnum::InvalidArgument,
+                    "Expected arguments of type (as, s)",
+                );
+                return;
+            };
+
+            let paths = uris_to_paths(&uris);
+            match method {
+                "ShowFolders" => show_folders(&app, &services, &paths, &startup_id),
+                "ShowItems" => show_items(&app, &services, &paths, &startup_id),
+                "ShowItemProperties" => show_item_properties(&app, &services, &paths, &startup_id),
+                _ => {
+                    invocation.return_error(
+                        gio::IOErrorEnum::NotSupported,
+                        &format!("Unknown method {}", method),
+                    );
+                    return;
+                }
+            }
+
+            invocation.return_value(None);
+        })
+        .build()
+}
+
+/// Converts `file://` URIs (or plain paths) to local paths, dropping anything
+/// that has no local representation.
+fn uris_to_paths(uris: &[String]) -> Vec<PathBuf> {
+    uris.iter()
+        .filter_map(|uri| {
+            if uri.starts_with('/') {
+                Some(PathBuf::from(uri))
+            } else {
+                gio::File::for_uri(uri).path()
+            }
+        })
+        .collect()
+}
+
+fn show_folders(app: &Application, services: &AppServices, folders: &[PathBuf], startup_id: &str) {
+    for folder in folders {
+        crate::open_window(app, services, folder, &[], Some(startup_id));
+    }
+}
+
+/// Opens one window per parent folder with the requested items selected.
+fn show_items(app: &Application, services: &AppServices, items: &[PathBuf], startup_id: &str) {
+    for (parent, selection) in group_by_parent(items) {
+        crate::open_window(app, services, &parent, &selection, Some(startup_id));
+    }
+}
+
+/// Like `show_items`, then opens a properties dialog for each item.
+fn show_item_properties(
+    app: &Application,
+    services: &AppServices,
+    items: &[PathBuf],
+    startup_id: &str,
+) {
+    for (parent, selection) in group_by_parent(items) {
+        let app_window = crate::open_window(app, services, &parent, &selection, Some(startup_id));
+        for item in &selection {
+            app_window.show_properties(item);
+        }
+    }
+}
+
+fn group_by_parent(items: &[PathBuf]) -> BTreeMap<PathBuf, Vec<PathBuf>> {
+    let mut by_parent: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for item in items {
+        let parent = item
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("/"));
+        by_parent.entry(parent).or_default().push(item.clone());
+    }
+    by_parent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn converts_uris_and_plain_paths() {
+        let uris = [
+            "file:///home/user/a%20b.txt".to_string(),
+            "/home/user/plain".to_string(),
+            "file:///tmp/caf%C3%A9".to_string(),
+        ];
+        assert_eq!(
+            uris_to_paths(&uris),
+            paths(&["/home/user/a b.txt", "/home/user/plain", "/tmp/café"])
+        );
+    }
+
+    #[test]
+    fn drops_uris_without_a_local_path() {
+        let uris = [
+            "https://example.com/file.txt".to_string(),
+            "file:///kept".to_string(),
+        ];
+        assert_eq!(uris_to_paths(&uris), paths(&["/kept"]));
+    }
+
+    #[test]
+    fn groups_items_by_parent() {
+        let items = paths(&["/a/x", "/b/y", "/a/z", "/"]);
+        let groups: Vec<(PathBuf, Vec<PathBuf>)> = group_by_parent(&items).into_iter().collect();
+        assert_eq!(
+            groups,
+            vec![
+                (PathBuf::from("/"), paths(&["/"])),
+                (PathBuf::from("/a"), paths(&["/a/x", "/a/z"])),
+                (PathBuf::from("/b"), paths(&["/b/y"])),
+            ]
+        );
+    }
+}
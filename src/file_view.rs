@@ -1,25 +1,267 @@
-use crate::file_info::FileInfo;
+use crate::file_info::{self, FileInfo, SizeFormat};
+use crate::folder_size::{FolderSize, FolderSizeCache};
+use crate::recursive_search::ContentMatch;
 use gtk::prelude::*;
 use gtk::{FlowBox, Label, ListBox, Orientation};
-use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::time::SystemTime;
 
-#[derive(Clone, Copy, PartialEq)]
+pub const DEFAULT_ZOOM_LEVEL: u32 = 2;
+pub const MAX_ZOOM_LEVEL: u32 = 4;
+
+/// Thumbnails kept per view before the cache starts over.
+const MAX_CACHED_THUMBNAILS: usize = 1000;
+
+/// Icon size, label width and item padding for one zoom level.
+struct ZoomSpec {
+    icon_size: i32,
+    label_width_chars: i32,
+    padding: i32,
+}
+
+const GRID_ZOOM_LEVELS: [ZoomSpec; 5] = [
+    ZoomSpec {
+        icon_size: 32,
+        label_width_chars: 12,
+        padding: 3,
+    },
+    ZoomSpec {
+        icon_size: 40,
+        label_width_chars: 16,
+        padding: 4,
+    },
+    ZoomSpec {
+        icon_size: 48,
+        label_width_chars: 20,
+        padding: 6,
+    },
+    ZoomSpec {
+        icon_size: 64,
+        label_width_chars: 24,
+        padding: 8,
+    },
+    ZoomSpec {
+        icon_size: 96,
+        label_width_chars: 28,
+        padding: 10,
+    },
+];
+
+// The smallest list level is the compact density for dense directories.
+const LIST_ZOOM_LEVELS: [ZoomSpec; 5] = [
+    ZoomSpec {
+        icon_size: 16,
+        label_width_chars: 10,
+        padding: 1,
+    },
+    ZoomSpec {
+        icon_size: 16,
+        label_width_chars: 12,
+        padding: 3,
+    },
+    ZoomSpec {
+        icon_size: 24,
+        label_width_chars: 12,
+        padding: 6,
+    },
+    ZoomSpec {
+        icon_size: 32,
+        label_width_chars: 14,
+        padding: 8,
+    },
+    ZoomSpec {
+        icon_size: 48,
+        label_width_chars: 16,
+        padding: 10,
+    },
+];
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ViewMode {
     Grid,
     List,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortColumn {
+    Name,
+    Size,
+    Type,
+    Modified,
+}
+
+impl SortColumn {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortColumn::Name => "name",
+            SortColumn::Size => "size",
+            SortColumn::Type => "type",
+            SortColumn::Modified => "modified",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(SortColumn::Name),
+            "size" => Some(SortColumn::Size),
+            "type" => Some(SortColumn::Type),
+            "modified" => Some(SortColumn::Modified),
+            _ => None,
+        }
+    }
+}
+
+/// Optional list view columns, shown after the standard ones.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListColumn {
+    Owner,
+    Group,
+    Permissions,
+    Mode,
+    Inode,
+    Links,
+    Device,
+    Accessed,
+    Created,
+}
+
+impl ListColumn {
+    pub const ALL: [ListColumn; 9] = [
+        ListColumn::Owner,
+        ListColumn::Group,
+        ListColumn::Permissions,
+        ListColumn::Mode,
+        ListColumn::Inode,
+        ListColumn::Links,
+        ListColumn::Device,
+        ListColumn::Accessed,
+        ListColumn::Created,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListColumn::Owner => "owner",
+            ListColumn::Group => "group",
+            ListColumn::Permissions => "permissions",
+            ListColumn::Mode => "mode",
+            ListColumn::Inode => "inode",
+            ListColumn::Links => "links",
+            ListColumn::Device => "device",
+            ListColumn::Accessed => "accessed",
+            ListColumn::Created => "created",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ListColumn::Owner => "Owner",
+            ListColumn::Group => "Group",
+            ListColumn::Permissions => "Permissions",
+            ListColumn::Mode => "Octal Permissions",
+            ListColumn::Inode => "Inode",
+            ListColumn::Links => "Hard Links",
+            ListColumn::Device => "Device",
+            ListColumn::Accessed => "Accessed",
+            ListColumn::Created => "Created",
+        }
+    }
+
+    /// The cell text for `file`; empty when the metadata is unknown.
+    fn text(&self, file: &FileInfo) -> String {
+        let Some(unix) = &file.unix else {
+            return String::new();
+        };
+        match self {
+            ListColumn::Owner => unix.owner_name(),
+            ListColumn::Group => unix.group_name(),
+            ListColumn::Permissions => unix.format_permissions(),
+            ListColumn::Mode => unix.format_mode(),
+            ListColumn::Inode => unix.inode.to_string(),
+            ListColumn::Links => unix.hard_links.to_string(),
+            ListColumn::Device => unix.device.to_string(),
+            ListColumn::Accessed => file_info::format_time(unix.accessed),
+            ListColumn::Created => unix.created.map(file_info::format_time).unwrap_or_default(),
+        }
+    }
+
+    fn width_chars(&self, zoom: &ZoomSpec) -> i32 {
+        match self {
+            ListColumn::Accessed | ListColumn::Created => zoom.label_width_chars + 8,
+            ListColumn::Permissions | ListColumn::Inode => zoom.label_width_chars,
+            _ => zoom.label_width_chars - 2,
+        }
+    }
+}
+
+/// How a folder is displayed. Remembered per folder, see `FolderViewStore`.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewOptions {
+    pub mode: ViewMode,
+    pub sort_column: SortColumn,
+    pub sort_descending: bool,
+    pub zoom_level: u32,
+    pub show_hidden: bool,
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        ViewOptions {
+            mode: ViewMode::Grid,
+            sort_column: SortColumn::Name,
+            sort_descending: false,
+            zoom_level: DEFAULT_ZOOM_LEVEL,
+            show_hidden: false,
+        }
+    }
+}
+
+/// Predicate deciding which loaded files are shown, e.g. while searching.
+pub type FileFilter = Rc<dyn Fn(&FileInfo) -> bool>;
+
 #[derive(Clone)]
 pub struct FileView {
     widget: gtk::Box,
     grid_view: FlowBox,
     list_view: ListBox,
+    placeholder: Label,
     current_mode: Rc<RefCell<ViewMode>>,
     current_path: Rc<RefCell<PathBuf>>,
+    /// False until the first `load_directory`, so that settings applied to a
+    /// new view don't list `current_path`'s placeholder.
+    has_directory: Rc<Cell<bool>>,
     files: Rc<RefCell<Vec<FileInfo>>>,
+    loaded_files: Rc<RefCell<Vec<FileInfo>>>,
+    filter: Rc<RefCell<Option<FileFilter>>>,
+    results_root: Rc<RefCell<Option<PathBuf>>>,
+    snippets: Rc<RefCell<HashMap<PathBuf, String>>>,
+    sort_column: Rc<Cell<SortColumn>>,
+    sort_descending: Rc<Cell<bool>>,
+    zoom_level: Rc<Cell<u32>>,
+    show_hidden: Rc<Cell<bool>>,
+    hide_backups: Rc<Cell<bool>>,
+    hidden_count: Rc<Cell<usize>>,
+    show_thumbnails: Rc<Cell<bool>>,
+    /// Scaled thumbnails by path and pixel size, with the modification time
+    /// of the image they were made from.
+    thumbnails: Rc<RefCell<HashMap<(PathBuf, i32), (SystemTime, gdk::Texture)>>>,
+    size_format: Rc<Cell<SizeFormat>>,
+    list_columns: Rc<RefCell<Vec<ListColumn>>>,
+    /// Where folder sizes are measured and kept, when enabled.
+    folder_sizes: Rc<RefCell<Option<FolderSizeCache>>>,
+    /// Stops the background work started for the previous listing.
+    loading_details: Rc<RefCell<Option<Arc<AtomicBool>>>>,
     on_activated: Rc<RefCell<Option<Rc<dyn Fn(PathBuf)>>>>,
 }
 
@@ -44,15 +286,40 @@ impl FileView {
             .selection_mode(gtk::SelectionMode::Multiple)
             .build();
 
+        let placeholder = Label::new(Some("No results"));
+        placeholder.add_css_class("dim-label");
+        placeholder.add_css_class("title-2");
+        placeholder.set_vexpand(true);
+        placeholder.set_visible(false);
+
         widget.append(&grid_view);
+        widget.append(&placeholder);
 
         let file_view = FileView {
             widget,
             grid_view,
             list_view,
+            placeholder,
             current_mode: Rc::new(RefCell::new(ViewMode::Grid)),
             current_path: Rc::new(RefCell::new(PathBuf::from("/"))),
+            has_directory: Rc::new(Cell::new(false)),
             files: Rc::new(RefCell::new(Vec::new())),
+            loaded_files: Rc::new(RefCell::new(Vec::new())),
+            filter: Rc::new(RefCell::new(None)),
+            results_root: Rc::new(RefCell::new(None)),
+            snippets: Rc::new(RefCell::new(HashMap::new())),
+            sort_column: Rc::new(Cell::new(SortColumn::Name)),
+            sort_descending: Rc::new(Cell::new(false)),
+            zoom_level: Rc::new(Cell::new(DEFAULT_ZOOM_LEVEL)),
+            show_hidden: Rc::new(Cell::new(false)),
+            hide_backups: Rc::new(Cell::new(true)),
+            hidden_count: Rc::new(Cell::new(0)),
+            show_thumbnails: Rc::new(Cell::new(true)),
+            thumbnails: Rc::new(RefCell::new(HashMap::new())),
+            size_format: Rc::new(Cell::new(SizeFormat::default())),
+            list_columns: Rc::new(RefCell::new(Vec::new())),
+            folder_sizes: Rc::new(RefCell::new(None)),
+            loading_details: Rc::new(RefCell::new(None)),
             on_activated: Rc::new(RefCell::new(None)),
         };
 
@@ -66,165 +333,638 @@ impl FileView {
     }
 
     pub fn set_view_mode(&self, mode: ViewMode) {
+        if self.switch_view(mode) {
+            self.reload();
+        }
+    }
+
+    /// Swaps the visible view widget, returning whether the mode changed.
+    fn switch_view(&self, mode: ViewMode) -> bool {
         let mut current_mode = self.current_mode.borrow_mut();
-        if *current_mode != mode {
-            *current_mode = mode;
-            drop(current_mode);
+        if *current_mode == mode {
+            return false;
+        }
+        *current_mode = mode;
+        drop(current_mode);
 
-            // Remove old view
-            if let Some(child) = self.widget.first_child() {
-                self.widget.remove(&child);
+        // Swap the old view for the new one, keeping the placeholder last
+        match mode {
+            ViewMode::Grid => {
+                self.widget.remove(&self.list_view);
+                self.widget.prepend(&self.grid_view);
             }
-
-            // Add new view
-            match mode {
-                ViewMode::Grid => self.widget.append(&self.grid_view),
-                ViewMode::List => self.widget.append(&self.list_view),
+            ViewMode::List => {
+                self.widget.remove(&self.grid_view);
+                self.widget.prepend(&self.list_view);
             }
+        }
+
+        true
+    }
 
-            // Reload with new view
-            let path = self.current_path.borrow().clone();
-            self.load_directory(&path);
+    pub fn view_options(&self) -> ViewOptions {
+        ViewOptions {
+            mode: *self.current_mode.borrow(),
+            sort_column: self.sort_column.get(),
+            sort_descending: self.sort_descending.get(),
+            zoom_level: self.zoom_level.get(),
+            show_hidden: self.show_hidden.get(),
         }
     }
 
-    pub fn load_directory(&self, path: &Path) {
-        *self.current_path.borrow_mut() = path.to_path_buf();
+    /// Applies `options` without reloading; the next `load_directory` uses them.
+    pub fn set_view_options(&self, options: ViewOptions) {
+        self.switch_view(options.mode);
+        self.sort_column.set(options.sort_column);
+        self.sort_descending.set(options.sort_descending);
+        self.zoom_level.set(options.zoom_level);
+        self.show_hidden.set(options.show_hidden);
+    }
 
-        // Clear existing items
-        while let Some(child) = self.grid_view.first_child() {
-            self.grid_view.remove(&child);
+    /// Changes the zoom level by `delta` steps, returning whether it changed.
+    pub fn zoom_by(&self, delta: i32) -> bool {
+        let current = self.zoom_level.get();
+        let level = current.saturating_add_signed(delta).min(MAX_ZOOM_LEVEL);
+        self.set_zoom_level(level)
+    }
+
+    /// Sets the zoom level, returning whether it changed.
+    pub fn set_zoom_level(&self, level: u32) -> bool {
+        let level = level.min(MAX_ZOOM_LEVEL);
+        if self.zoom_level.replace(level) == level {
+            return false;
         }
-        while let Some(child) = self.list_view.first_child() {
-            self.list_view.remove(&child);
+        self.reload();
+        true
+    }
+
+    fn zoom_spec(&self) -> &'static ZoomSpec {
+        let level = self.zoom_level.get().min(MAX_ZOOM_LEVEL) as usize;
+        match *self.current_mode.borrow() {
+            ViewMode::Grid => &GRID_ZOOM_LEVELS[level],
+            ViewMode::List => &LIST_ZOOM_LEVELS[level],
+        }
+    }
+
+    pub fn set_sort(&self, column: SortColumn, descending: bool) {
+        self.sort_column.set(column);
+        self.sort_descending.set(descending);
+        self.reload();
+    }
+
+    pub fn set_show_hidden(&self, show_hidden: bool) {
+        if self.show_hidden.replace(show_hidden) != show_hidden {
+            self.reload();
+        }
+    }
+
+    pub fn set_hide_backups(&self, hide_backups: bool) {
+        if self.hide_backups.replace(hide_backups) != hide_backups {
+            self.reload();
+        }
+    }
+
+    pub fn set_show_thumbnails(&self, show_thumbnails: bool) {
+        if self.show_thumbnails.replace(show_thumbnails) != show_thumbnails {
+            self.reload();
+        }
+    }
+
+    pub fn set_size_format(&self, format: SizeFormat) {
+        if self.size_format.replace(format) != format {
+            self.reload();
+        }
+    }
+
+    pub fn set_list_columns(&self, columns: Vec<ListColumn>) {
+        if *self.list_columns.borrow() != columns {
+            *self.list_columns.borrow_mut() = columns;
+            self.refresh_items();
         }
+    }
+
+    /// Measures the folders being listed in the background and shows their
+    /// total sizes, or only their item counts when `None`.
+    pub fn set_folder_sizes(&self, cache: Option<FolderSizeCache>) {
+        let enabled = cache.is_some();
+        if self.folder_sizes.replace(cache).is_some() != enabled {
+            self.reload();
+        }
+    }
+
+    pub fn set_single_click(&self, single_click: bool) {
+        self.grid_view.set_activate_on_single_click(single_click);
+        self.list_view.set_activate_on_single_click(single_click);
+    }
+
+    /// Shows only the loaded files accepted by `filter`, or all of them.
+    pub fn set_filter(&self, filter: Option<FileFilter>) {
+        *self.filter.borrow_mut() = filter;
+        self.refresh_items();
+    }
+
+    /// Clears the view to show search results found under `root`, which are
+    /// then streamed in with `append_results`.
+    pub fn begin_results(&self, root: &Path) {
+        self.stop_loading_details();
+        *self.results_root.borrow_mut() = Some(root.to_path_buf());
+        *self.filter.borrow_mut() = None;
+        self.snippets.borrow_mut().clear();
+        self.loaded_files.borrow_mut().clear();
+        self.hidden_count.set(0);
+        self.refresh_items();
+    }
+
+    pub fn append_results(&self, results: Vec<FileInfo>) {
+        if results.is_empty() {
+            return;
+        }
+
+        let mode = *self.current_mode.borrow();
+        match mode {
+            ViewMode::Grid => self.populate_grid_view(&results),
+            ViewMode::List => self.populate_list_view(&results),
+        }
+        self.placeholder.set_visible(false);
+
+        let unsniffed = results
+            .iter()
+            .filter(|file| file.needs_sniffing)
+            .map(|file| file.path.clone())
+            .collect();
+        self.loaded_files
+            .borrow_mut()
+            .extend(results.iter().cloned());
+        self.files.borrow_mut().extend(results);
+        self.load_details(unsniffed, Vec::new(), None);
+    }
+
+    /// Appends content search results, showing each file's matching line.
+    pub fn append_content_results(&self, matches: Vec<ContentMatch>) {
+        self.append_annotated_results(
+            matches
+                .into_iter()
+                .map(|content_match| {
+                    let snippet = content_match.snippet();
+                    (content_match.file, snippet)
+                })
+                .collect(),
+        );
+    }
+
+    /// Appends results with a line of text shown under each name.
+    pub fn append_annotated_results(&self, results: Vec<(FileInfo, String)>) {
+        let mut files = Vec::with_capacity(results.len());
+        {
+            let mut snippets = self.snippets.borrow_mut();
+            for (file, snippet) in results {
+                snippets.insert(file.path.clone(), snippet);
+                files.push(file);
+            }
+        }
+        self.append_results(files);
+    }
+
+    pub fn is_showing_results(&self) -> bool {
+        self.results_root.borrow().is_some()
+    }
+
+    pub fn load_directory(&self, path: &Path) {
+        *self.current_path.borrow_mut() = path.to_path_buf();
+        self.has_directory.set(true);
+        *self.results_root.borrow_mut() = None;
+        self.snippets.borrow_mut().clear();
+        self.stop_loading_details();
 
         // Read directory
         let entries = match fs::read_dir(path) {
             Ok(entries) => entries,
             Err(e) => {
                 eprintln!("Failed to read directory: {}", e);
+                self.loaded_files.borrow_mut().clear();
+                self.hidden_count.set(0);
+                self.refresh_items();
                 return;
             }
         };
 
+        let show_hidden = self.show_hidden.get();
+        let hide_backups = self.hide_backups.get();
+        let hidden_names = file_info::read_hidden_names(path);
+        let folder_sizes = self.folder_sizes.borrow().clone();
+        let mut unsniffed = Vec::new();
+        let mut uncounted = Vec::new();
+        let mut unmeasured = Vec::new();
+        let mut hidden_count = 0;
         let mut files = Vec::new();
         for entry in entries {
             if let Ok(entry) = entry {
-                if let Ok(file_info) = FileInfo::from_path(&entry.path()) {
+                if let Ok(mut file_info) = FileInfo::from_path(&entry.path()) {
+                    let hidden = file_info.is_hidden
+                        || hidden_names.contains(&file_info.name)
+                        || (hide_backups && file_info.is_backup());
+                    if hidden && !show_hidden {
+                        hidden_count += 1;
+                        continue;
+                    }
+
+                    if file_info.needs_sniffing {
+                        unsniffed.push(file_info.path.clone());
+                    }
+                    if file_info.is_dir {
+                        uncounted.push(file_info.path.clone());
+                        if let Some(cache) = &folder_sizes {
+                            match cache.get(&file_info.path) {
+                                Some(size) => file_info.folder_size = Some(size),
+                                None => unmeasured.push(file_info.path.clone()),
+                            }
+                        }
+                    }
                     files.push(file_info);
                 }
             }
         }
+        self.hidden_count.set(hidden_count);
+
+        self.sort_files(&mut files);
+        *self.loaded_files.borrow_mut() = files;
+        self.refresh_items();
+
+        self.load_details(
+            unsniffed,
+            uncounted,
+            folder_sizes.map(|cache| (cache, unmeasured)),
+        );
+    }
 
-        // Sort: directories first, then by name
+    /// Sorts directories first, then by the chosen column.
+    fn sort_files(&self, files: &mut [FileInfo]) {
+        let sort_column = self.sort_column.get();
+        let sort_descending = self.sort_descending.get();
+        let size_format = self.size_format.get();
         files.sort_by(|a, b| match (a.is_dir, b.is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => {
+                let ordering = compare_files(a, b, sort_column, size_format);
+                if sort_descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
         });
+    }
 
-        *self.files.borrow_mut() = files.clone();
+    /// Fills in what is too slow to find out while listing, on worker
+    /// threads: first the types of files whose names don't settle them and
+    /// the item counts of `uncounted` folders, then the sizes of `unmeasured`
+    /// folders. The rows are updated after each step.
+    fn load_details(
+        &self,
+        unsniffed: Vec<PathBuf>,
+        uncounted: Vec<PathBuf>,
+        unmeasured: Option<(FolderSizeCache, Vec<PathBuf>)>,
+    ) {
+        let unmeasured = unmeasured.filter(|(_, folders)| !folders.is_empty());
+        if unsniffed.is_empty() && uncounted.is_empty() && unmeasured.is_none() {
+            return;
+        }
+
+        let cancelled = self
+            .loading_details
+            .borrow_mut()
+            .get_or_insert_with(Default::default)
+            .clone();
+
+        let file_view = self.clone();
+        glib::spawn_future_local(async move {
+            if !unsniffed.is_empty() || !uncounted.is_empty() {
+                let worker_cancelled = cancelled.clone();
+                let found = gio::spawn_blocking(move || {
+                    let is_cancelled = || worker_cancelled.load(atomic::Ordering::Relaxed);
+                    let sniffed = unsniffed
+                        .into_iter()
+                        .take_while(|_| !is_cancelled())
+                        .filter_map(|path| {
+                            let mime_type = file_info::sniff_mime_type(&path)?;
+                            Some((path, mime_type))
+                        })
+                        .collect::<HashMap<PathBuf, String>>();
+                    let counted = uncounted
+                        .into_iter()
+                        .take_while(|_| !is_cancelled())
+                        .filter_map(|folder| {
+                            let count = fs::read_dir(&folder).ok()?.count() as u64;
+                            Some((folder, count))
+                        })
+                        .collect::<HashMap<PathBuf, u64>>();
+                    (sniffed, counted)
+                })
+                .await;
+
+                let Ok((sniffed, counted)) = found else {
+                    return;
+                };
+                if cancelled.load(atomic::Ordering::Relaxed) {
+                    return;
+                }
+                file_view.update_files(|file| {
+                    if let Some(mime_type) = sniffed.get(&file.path) {
+                        file.mime_type = mime_type.clone();
+                        file.needs_sniffing = false;
+                    }
+                    if let Some(count) = counted.get(&file.path) {
+                        file.item_count = Some(*count);
+                    }
+                });
+            }
+
+            if let Some((cache, folders)) = unmeasured {
+                let worker_cancelled = cancelled.clone();
+                let measured = gio::spawn_blocking(move || {
+                    folders
+                        .into_iter()
+                        .filter_map(|folder| {
+                            let size = cache.measure(&folder, &worker_cancelled)?;
+                            Some((folder, size))
+                        })
+                        .collect::<HashMap<PathBuf, FolderSize>>()
+                })
+                .await;
+
+                let Ok(measured) = measured else {
+                    return;
+                };
+                if cancelled.load(atomic::Ordering::Relaxed) {
+                    return;
+                }
+                file_view.update_files(|file| {
+                    if let Some(size) = measured.get(&file.path) {
+                        file.folder_size = Some(*size);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Changes the loaded files in place and redraws them, re-sorting a
+    /// listing and keeping the selection.
+    fn update_files(&self, update: impl FnMut(&mut FileInfo)) {
+        let selection = self.selected_paths();
+        {
+            let mut loaded_files = self.loaded_files.borrow_mut();
+            loaded_files.iter_mut().for_each(update);
+            if !self.is_showing_results() {
+                self.sort_files(&mut loaded_files);
+            }
+        }
+        self.refresh_items();
+        if !selection.is_empty() {
+            self.select_paths(&selection);
+        }
+    }
+
+    fn stop_loading_details(&self) {
+        if let Some(cancelled) = self.loading_details.borrow_mut().take() {
+            cancelled.store(true, atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Rebuilds the visible items from the loaded files and the active filter.
+    fn refresh_items(&self) {
+        // Clear existing items
+        while let Some(child) = self.grid_view.first_child() {
+            self.grid_view.remove(&child);
+        }
+        while let Some(child) = self.list_view.first_child() {
+            self.list_view.remove(&child);
+        }
+
+        let filter = self.filter.borrow().clone();
+        let files: Vec<FileInfo> = match &filter {
+            Some(filter) => self
+                .loaded_files
+                .borrow()
+                .iter()
+                .filter(|file| filter(file))
+                .cloned()
+                .collect(),
+            None => self.loaded_files.borrow().clone(),
+        };
+
+        let narrowed = filter.is_some() || self.is_showing_results();
+        self.placeholder.set_visible(narrowed && files.is_empty());
 
         let mode = *self.current_mode.borrow();
         match mode {
             ViewMode::Grid => self.populate_grid_view(&files),
             ViewMode::List => self.populate_list_view(&files),
         }
+
+        *self.files.borrow_mut() = files;
     }
 
     fn populate_grid_view(&self, files: &[FileInfo]) {
+        let zoom = self.zoom_spec();
+        let snippets = self.snippets.borrow();
         for file in files {
-            let item_box = gtk::Box::new(Orientation::Vertical, 6);
-            item_box.set_margin_start(6);
-            item_box.set_margin_end(6);
-            item_box.set_margin_top(6);
-            item_box.set_margin_bottom(6);
+            let item_box = gtk::Box::new(Orientation::Vertical, zoom.padding);
+            item_box.set_margin_start(zoom.padding);
+            item_box.set_margin_end(zoom.padding);
+            item_box.set_margin_top(zoom.padding);
+            item_box.set_margin_bottom(zoom.padding);
 
-            let icon = gtk::Image::from_icon_name(file.icon_name());
-            icon.set_pixel_size(48);
-            item_box.append(&icon);
+            let icon = gtk::Image::from_gicon(&file.icon());
+            icon.set_pixel_size(zoom.icon_size);
+            if self.show_thumbnails.get() && file.has_thumbnail() {
+                self.load_thumbnail(&icon, file, zoom.icon_size);
+            }
+            item_box.append(&with_link_emblem(&icon, file));
 
             let label = Label::new(Some(&file.name));
             label.set_ellipsize(gtk::pango::EllipsizeMode::End);
-            label.set_max_width_chars(20);
+            label.set_max_width_chars(zoom.label_width_chars);
+            if file.is_dangling {
+                label.add_css_class("dim-label");
+            }
             item_box.append(&label);
 
-            // Store path as a property using glib
-            let path_str = file.path.to_string_lossy().to_string();
-            item_box.set_property("tooltip-text", &path_str);
+            if let Some(snippet) = snippets.get(&file.path) {
+                let snippet_label = Label::new(Some(snippet));
+                snippet_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                snippet_label.set_max_width_chars(zoom.label_width_chars);
+                snippet_label.add_css_class("dim-label");
+                snippet_label.add_css_class("caption");
+                item_box.append(&snippet_label);
+            }
+
+            item_box.set_tooltip_text(Some(&file.tooltip()));
 
             self.grid_view.insert(&item_box, -1);
         }
     }
 
+    /// Replaces `image`'s icon with a thumbnail of `file`, decoded at `size`
+    /// pixels in the background and cached.
+    fn load_thumbnail(&self, image: &gtk::Image, file: &FileInfo, size: i32) {
+        let key = (file.path.clone(), size);
+        if let Some((modified, texture)) = self.thumbnails.borrow().get(&key) {
+            if *modified == file.modified {
+                image.set_paintable(Some(texture));
+                return;
+            }
+        }
+
+        let image_weak = image.downgrade();
+        let thumbnails = self.thumbnails.clone();
+        let modified = file.modified;
+        glib::spawn_future_local(async move {
+            let Ok(stream) = gio::File::for_path(&key.0)
+                .read_future(glib::Priority::LOW)
+                .await
+            else {
+                return;
+            };
+            let pixbuf = match gtk::gdk_pixbuf::Pixbuf::from_stream_at_scale_future(
+                &stream, size, size, true,
+            )
+            .await
+            {
+                Ok(pixbuf) => pixbuf,
+                Err(e) => {
+                    eprintln!("Failed to load thumbnail for {}: {}", key.0.display(), e);
+                    return;
+                }
+            };
+            let texture = gdk::Texture::for_pixbuf(&pixbuf);
+
+            let mut thumbnails = thumbnails.borrow_mut();
+            if thumbnails.len() >= MAX_CACHED_THUMBNAILS {
+                thumbnails.clear();
+            }
+            thumbnails.insert(key, (modified, texture.clone()));
+            if let Some(image) = image_weak.upgrade() {
+                image.set_paintable(Some(&texture));
+            }
+        });
+    }
+
     fn populate_list_view(&self, files: &[FileInfo]) {
+        let zoom = self.zoom_spec();
+        let results_root = self.results_root.borrow().clone();
+        let snippets = self.snippets.borrow();
         for file in files {
             let row_box = gtk::Box::new(Orientation::Horizontal, 12);
             row_box.set_margin_start(12);
             row_box.set_margin_end(12);
-            row_box.set_margin_top(6);
-            row_box.set_margin_bottom(6);
+            row_box.set_margin_top(zoom.padding);
+            row_box.set_margin_bottom(zoom.padding);
 
-            let icon = gtk::Image::from_icon_name(file.icon_name());
-            icon.set_pixel_size(24);
-            row_box.append(&icon);
+            let icon = gtk::Image::from_gicon(&file.icon());
+            icon.set_pixel_size(zoom.icon_size);
+            row_box.append(&with_link_emblem(&icon, file));
 
             let name_label = Label::new(Some(&file.name));
             name_label.set_halign(gtk::Align::Start);
-            name_label.set_hexpand(true);
-            row_box.append(&name_label);
+            if file.is_dangling {
+                name_label.add_css_class("dim-label");
+            }
+            match snippets.get(&file.path) {
+                Some(snippet) => {
+                    // Content matches show the matching line below the name
+                    let name_box = gtk::Box::new(Orientation::Vertical, 2);
+                    name_box.set_hexpand(true);
+                    name_box.append(&name_label);
+
+                    let snippet_label = Label::new(Some(snippet));
+                    snippet_label.set_halign(gtk::Align::Start);
+                    snippet_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                    snippet_label.add_css_class("dim-label");
+                    snippet_label.add_css_class("caption");
+                    name_box.append(&snippet_label);
+
+                    row_box.append(&name_box);
+                }
+                None => {
+                    name_label.set_hexpand(true);
+                    row_box.append(&name_label);
+                }
+            }
+
+            // Search results also show where they were found
+            if let Some(root) = &results_root {
+                let location_label = Label::new(Some(&relative_location(file, root)));
+                location_label.set_halign(gtk::Align::Start);
+                location_label.set_ellipsize(gtk::pango::EllipsizeMode::Start);
+                location_label.set_width_chars(zoom.label_width_chars + 8);
+                location_label.add_css_class("dim-label");
+                row_box.append(&location_label);
+            }
 
-            let size_label = Label::new(Some(&file.format_size()));
-            size_label.set_width_chars(12);
+            let size_label = Label::new(Some(&file.format_size(self.size_format.get())));
+            size_label.set_width_chars(zoom.label_width_chars);
             size_label.add_css_class("dim-label");
             row_box.append(&size_label);
 
+            // Links show their target in place of the type
+            let type_text = match &file.link_target {
+                Some(target) => format!("→ {}", target.display()),
+                None => file.type_description(),
+            };
+            let type_label = Label::new(Some(&type_text));
+            type_label.set_width_chars(zoom.label_width_chars + 4);
+            type_label.set_max_width_chars(zoom.label_width_chars + 4);
+            type_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            type_label.add_css_class("dim-label");
+            row_box.append(&type_label);
+
             let date_label = Label::new(Some(&file.format_modified()));
-            date_label.set_width_chars(20);
+            date_label.set_width_chars(zoom.label_width_chars + 8);
             date_label.add_css_class("dim-label");
             row_box.append(&date_label);
 
-            // Store path as a property using glib
-            let path_str = file.path.to_string_lossy().to_string();
-            row_box.set_property("tooltip-text", &path_str);
+            for column in self.list_columns.borrow().iter() {
+                let column_label = Label::new(Some(&column.text(file)));
+                column_label.set_width_chars(column.width_chars(zoom));
+                column_label.set_xalign(0.0);
+                column_label.add_css_class("dim-label");
+                row_box.append(&column_label);
+            }
+
+            row_box.set_tooltip_text(Some(&file.tooltip()));
 
             self.list_view.append(&row_box);
         }
     }
 
     fn setup_activation(&self) {
-        // Grid view activation (double-click)
-        let on_activated = self.on_activated.clone();
+        // Grid view activation (double-click); items are in `files` order
+        let file_view = self.clone();
         self.grid_view.connect_child_activated(move |_, child| {
-            // Get path from tooltip-text property
-            if let Some(path_str) = child.property::<Option<String>>("tooltip-text") {
-                if !path_str.is_empty() {
-                    let path = PathBuf::from(path_str);
-                    if let Some(callback) = on_activated.borrow().as_ref() {
-                        callback(path);
-                    }
-                }
-            }
+            file_view.activate_index(child.index());
         });
 
         // List view activation (double-click)
-        let on_activated = self.on_activated.clone();
+        let file_view = self.clone();
         self.list_view.connect_row_activated(move |_, row| {
-            // Get path from tooltip-text property of the row's child
-            if let Some(child) = row.child() {
-                if let Some(path_str) = child.property::<Option<String>>("tooltip-text") {
-                    if !path_str.is_empty() {
-                        let path = PathBuf::from(path_str);
-                        if let Some(callback) = on_activated.borrow().as_ref() {
-                            callback(path);
-                        }
-                    }
-                }
-            }
+            file_view.activate_index(row.index());
         });
     }
 
+    fn activate_index(&self, index: i32) {
+        let path = self
+            .files
+            .borrow()
+            .get(index as usize)
+            .map(|file| file.path.clone());
+        if let Some(path) = path {
+            if let Some(callback) = self.on_activated.borrow().as_ref() {
+                callback(path);
+            }
+        }
+    }
+
     pub fn connect_activated<F>(&self, callback: F)
     where
         F: Fn(PathBuf) + 'static,
@@ -235,4 +975,171 @@ impl FileView {
     pub fn item_count(&self) -> usize {
         self.files.borrow().len()
     }
+
+    /// Number of entries in the current folder that are filtered out as hidden.
+    pub fn hidden_count(&self) -> usize {
+        self.hidden_count.get()
+    }
+
+    pub fn current_path(&self) -> PathBuf {
+        self.current_path.borrow().clone()
+    }
+
+    /// Lists the current folder again, or redraws the search results or
+    /// recent files being shown, which can't be listed again.
+    pub fn reload(&self) {
+        if self.is_showing_results() {
+            self.refresh_items();
+            return;
+        }
+        if !self.has_directory.get() {
+            return;
+        }
+        let path = self.current_path();
+        self.load_directory(&path);
+    }
+
+    pub fn selected_paths(&self) -> Vec<PathBuf> {
+        self.selected_files()
+            .into_iter()
+            .map(|file| file.path)
+            .collect()
+    }
+
+    /// The file shown for each selected item.
+    pub fn selected_files(&self) -> Vec<FileInfo> {
+        let files = self.files.borrow();
+        let indices: Vec<i32> = match *self.current_mode.borrow() {
+            ViewMode::Grid => self
+                .grid_view
+                .selected_children()
+                .iter()
+                .map(|child| child.index())
+                .collect(),
+            ViewMode::List => self
+                .list_view
+                .selected_rows()
+                .iter()
+                .map(|row| row.index())
+                .collect(),
+        };
+
+        indices
+            .into_iter()
+            .filter_map(|index| files.get(index as usize))
+            .cloned()
+            .collect()
+    }
+
+    /// Prepares a context menu at `x`, `y` (in the view's coordinates): the
+    /// item there is selected unless it already is, and empty space clears
+    /// the selection.
+    pub fn select_item_at(&self, x: f64, y: f64) {
+        let picked = self.widget.pick(x, y, gtk::PickFlags::DEFAULT);
+        let mut widget = picked;
+        while let Some(current) = widget {
+            if let Some(child) = current.downcast_ref::<gtk::FlowBoxChild>() {
+                if !child.is_selected() {
+                    self.grid_view.unselect_all();
+                    self.grid_view.select_child(child);
+                }
+                return;
+            }
+            if let Some(row) = current.downcast_ref::<gtk::ListBoxRow>() {
+                if !row.is_selected() {
+                    self.list_view.unselect_all();
+                    self.list_view.select_row(Some(row));
+                }
+                return;
+            }
+            if &current == self.widget.upcast_ref::<gtk::Widget>() {
+                break;
+            }
+            widget = current.parent();
+        }
+
+        self.grid_view.unselect_all();
+        self.list_view.unselect_all();
+    }
+
+    /// Selects the items matching `paths` and scrolls the first one into view.
+    pub fn select_paths(&self, paths: &[PathBuf]) {
+        self.grid_view.unselect_all();
+        self.list_view.unselect_all();
+
+        let files = self.files.borrow();
+        let mut focused = false;
+        for (index, file) in files.iter().enumerate() {
+            if !paths.contains(&file.path) {
+                continue;
+            }
+
+            let widget: Option<gtk::Widget> = match *self.current_mode.borrow() {
+                ViewMode::Grid => self.grid_view.child_at_index(index as i32).map(|child| {
+                    self.grid_view.select_child(&child);
+                    child.upcast()
+                }),
+                ViewMode::List => self.list_view.row_at_index(index as i32).map(|row| {
+                    self.list_view.select_row(Some(&row));
+                    row.upcast()
+                }),
+            };
+
+            if let (false, Some(widget)) = (focused, widget) {
+                widget.grab_focus();
+                focused = true;
+            }
+        }
+    }
+}
+
+fn compare_files(a: &FileInfo, b: &FileInfo, column: SortColumn, format: SizeFormat) -> Ordering {
+    let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    match column {
+        SortColumn::Name => by_name(),
+        // Folders by measured size, falling back to their item counts
+        SortColumn::Size => a
+            .display_size(format)
+            .cmp(&b.display_size(format))
+            .then(a.item_count.cmp(&b.item_count))
+            .then_with(by_name),
+        SortColumn::Type => a
+            .type_description()
+            .to_lowercase()
+            .cmp(&b.type_description().to_lowercase())
+            .then_with(by_name),
+        SortColumn::Modified => a.modified.cmp(&b.modified).then_with(by_name),
+    }
+}
+
+/// Overlays a link emblem on the icons of symbolic links.
+fn with_link_emblem(icon: &gtk::Image, file: &FileInfo) -> gtk::Widget {
+    if !file.is_symlink() {
+        return icon.clone().upcast();
+    }
+
+    let emblem_name = if file.is_dangling {
+        "emblem-unreadable"
+    } else {
+        "emblem-symbolic-link"
+    };
+    let emblem = gtk::Image::from_icon_name(emblem_name);
+    emblem.set_pixel_size((icon.pixel_size() / 3).max(8));
+    emblem.set_halign(gtk::Align::End);
+    emblem.set_valign(gtk::Align::End);
+
+    let overlay = gtk::Overlay::new();
+    overlay.set_child(Some(icon));
+    overlay.add_overlay(&emblem);
+    overlay.upcast()
+}
+
+/// The folder containing `file`, relative to the search `root`.
+fn relative_location(file: &FileInfo, root: &Path) -> String {
+    let parent = file.path.parent().unwrap_or(root);
+    match parent.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.to_string_lossy().to_string(),
+        Err(_) => parent.to_string_lossy().to_string(),
+    }
 }
@@ -0,0 +1,109 @@
+use crate::recursive_search::{walk, WalkOptions};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Total size and number of entries below a folder.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FolderSize {
+    /// Apparent size of the files.
+    pub bytes: u64,
+    /// Space taken on disk by the files and folders.
+    pub allocated: u64,
+    pub files: u64,
+    pub folders: u64,
+}
+
+impl FolderSize {
+    /// "12 items" for the entries found so far.
+    pub fn describe_items(&self) -> String {
+        let count = self.files + self.folders;
+        if count == 1 {
+            "1 item".to_string()
+        } else {
+            format!("{} items", count)
+        }
+    }
+}
+
+/// Adds up everything below `folder`, without following symbolic links or
+/// leaving its filesystem. Blocks, so run it off the main thread.
+pub fn measure(folder: &Path, cancelled: &AtomicBool) -> FolderSize {
+    let options = WalkOptions {
+        skip_hidden: false,
+        follow_symlinks: false,
+        same_filesystem: true,
+        respect_gitignore: false,
+    };
+
+    let mut size = FolderSize::default();
+    walk(folder, options, cancelled, |path, _| {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return false;
+        };
+        size.allocated += metadata.blocks() * 512;
+        if metadata.is_dir() {
+            size.folders += 1;
+        } else {
+            size.files += 1;
+            size.bytes += metadata.len();
+        }
+        true
+    });
+    size
+}
+
+/// Measured folder sizes, shared by all windows and their worker threads.
+///
+/// An entry is reused while the folder's modification time is unchanged.
+/// That time only changes when entries are added, removed or renamed directly
+/// inside the folder, so a size can lag behind files growing further down
+/// until the folder itself changes.
+#[derive(Clone, Default)]
+pub struct FolderSizeCache {
+    entries: Arc<Mutex<HashMap<PathBuf, (SystemTime, FolderSize)>>>,
+}
+
+impl FolderSizeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached size of `folder`, if it hasn't been modified since.
+    pub fn get(&self, folder: &Path) -> Option<FolderSize> {
+        let modified = modified_time(folder)?;
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(folder)
+            .filter(|(measured_modified, _)| *measured_modified == modified)
+            .map(|(_, size)| *size)
+    }
+
+    /// The cached size of `folder`, measuring it when missing or outdated.
+    /// Blocks; returns `None` if `cancelled` was set before it finished.
+    pub fn measure(&self, folder: &Path, cancelled: &AtomicBool) -> Option<FolderSize> {
+        if let Some(size) = self.get(folder) {
+            return Some(size);
+        }
+
+        let modified = modified_time(folder)?;
+        let size = measure(folder, cancelled);
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(folder.to_path_buf(), (modified, size));
+        Some(size)
+    }
+}
+
+fn modified_time(folder: &Path) -> Option<SystemTime> {
+    fs::metadata(folder).and_then(|m| m.modified()).ok()
+}
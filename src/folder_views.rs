@@ -0,0 +1,63 @@
+use crate::file_view::ViewOptions;
+use crate::settings;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+fn database_path() -> PathBuf {
+    settings::data_dir().join("folder-views.json")
+}
+
+/// Per-folder view options, keyed by absolute path and stored in
+/// `$XDG_DATA_HOME/zfile/folder-views.json`. Folders without an entry use the
+/// defaults from the settings.
+#[derive(Clone)]
+pub struct FolderViewStore {
+    views: Rc<RefCell<HashMap<String, ViewOptions>>>,
+}
+
+impl FolderViewStore {
+    pub fn load() -> Self {
+        let views =
+            settings::load_json(&database_path(), "folder view settings").unwrap_or_default();
+
+        FolderViewStore {
+            views: Rc::new(RefCell::new(views)),
+        }
+    }
+
+    pub fn get(&self, folder: &Path) -> Option<ViewOptions> {
+        self.views.borrow().get(&key(folder)).copied()
+    }
+
+    pub fn set(&self, folder: &Path, options: ViewOptions) {
+        let previous = self.views.borrow_mut().insert(key(folder), options);
+        if previous != Some(options) {
+            self.save();
+        }
+    }
+
+    pub fn remove(&self, folder: &Path) {
+        if self.views.borrow_mut().remove(&key(folder)).is_some() {
+            self.save();
+        }
+    }
+
+    pub fn clear(&self) {
+        self.views.borrow_mut().clear();
+        self.save();
+    }
+
+    fn save(&self) {
+        settings::save_json(
+            &database_path(),
+            &*self.views.borrow(),
+            "folder view settings",
+        );
+    }
+}
+
+fn key(folder: &Path) -> String {
+    folder.to_string_lossy().to_string()
+}
@@ -0,0 +1,162 @@
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Locations beyond this many are forgotten, least frecent first.
+const MAX_LOCATIONS: usize = 500;
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn database_path() -> PathBuf {
+    settings::data_dir().join("locations.json")
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct Visits {
+    count: u32,
+    /// Seconds since the Unix epoch.
+    last_visit: u64,
+}
+
+impl Visits {
+    /// Visit count weighted by how recently the location was last visited.
+    fn score(&self, now: u64) -> u64 {
+        let age = now.saturating_sub(self.last_visit);
+        let weight = match age {
+            age if age < 4 * DAY => 100,
+            age if age < 14 * DAY => 70,
+            age if age < 31 * DAY => 50,
+            age if age < 90 * DAY => 30,
+            _ => 10,
+        };
+        u64::from(self.count) * weight
+    }
+}
+
+/// Folders the user has visited, ranked by frecency (frequency and recency),
+/// stored in `$XDG_DATA_HOME/zfile/locations.json`.
+#[derive(Clone)]
+pub struct FrecencyStore {
+    visits: Rc<RefCell<HashMap<String, Visits>>>,
+}
+
+impl FrecencyStore {
+    pub fn load() -> Self {
+        let visits = settings::load_json(&database_path(), "visited locations").unwrap_or_default();
+
+        FrecencyStore {
+            visits: Rc::new(RefCell::new(visits)),
+        }
+    }
+
+    pub fn record(&self, folder: &Path) {
+        let now = now();
+        {
+            let mut visits = self.visits.borrow_mut();
+            let entry = visits.entry(key(folder)).or_insert(Visits {
+                count: 0,
+                last_visit: now,
+            });
+            entry.count = entry.count.saturating_add(1);
+            entry.last_visit = now;
+
+            if visits.len() > MAX_LOCATIONS {
+                let mut scores: Vec<(String, u64)> = visits
+                    .iter()
+                    .map(|(key, visits)| (key.clone(), visits.score(now)))
+                    .collect();
+                scores.sort_by_key(|(_, score)| *score);
+                for (key, _) in scores.into_iter().take(visits.len() - MAX_LOCATIONS) {
+                    visits.remove(&key);
+                }
+            }
+        }
+        self.save();
+    }
+
+    pub fn score(&self, folder: &Path) -> u64 {
+        self.visits
+            .borrow()
+            .get(&key(folder))
+            .map(|visits| visits.score(now()))
+            .unwrap_or(0)
+    }
+
+    /// Visited folders that still exist, most frecent first.
+    pub fn list(&self) -> Vec<PathBuf> {
+        let now = now();
+        let mut locations: Vec<(u64, PathBuf)> = self
+            .visits
+            .borrow()
+            .iter()
+            .map(|(key, visits)| (visits.score(now), PathBuf::from(key)))
+            .filter(|(_, path)| path.is_dir())
+            .collect();
+        locations.sort_by(|a, b| b.0.cmp(&a.0));
+        locations.into_iter().map(|(_, path)| path).collect()
+    }
+
+    fn save(&self) {
+        settings::save_json(
+            &database_path(),
+            &*self.visits.borrow(),
+            "visited locations",
+        );
+    }
+}
+
+fn key(folder: &Path) -> String {
+    folder.to_string_lossy().to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn visited(count: u32, days_ago: u64) -> Visits {
+        Visits {
+            count,
+            last_visit: NOW - days_ago * DAY,
+        }
+    }
+
+    #[test]
+    fn decays_with_age() {
+        let scores: Vec<u64> = [0, 3, 4, 13, 14, 30, 31, 89, 90, 365]
+            .iter()
+            .map(|days| visited(2, *days).score(NOW))
+            .collect();
+        assert_eq!(scores, [200, 200, 140, 140, 100, 100, 60, 60, 20, 20]);
+    }
+
+    #[test]
+    fn weighs_visit_count() {
+        assert_eq!(visited(0, 0).score(NOW), 0);
+        assert!(visited(5, 0).score(NOW) > visited(4, 0).score(NOW));
+        // frequent old visits can outrank a single recent one
+        assert!(visited(20, 100).score(NOW) > visited(1, 0).score(NOW));
+        assert!(visited(2, 100).score(NOW) < visited(1, 0).score(NOW));
+    }
+
+    #[test]
+    fn treats_future_visits_as_recent() {
+        let visits = Visits {
+            count: 1,
+            last_visit: NOW + DAY,
+        };
+        assert_eq!(visits.score(NOW), 100);
+    }
+}
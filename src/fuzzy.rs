@@ -0,0 +1,110 @@
+const MATCH_SCORE: i64 = 1;
+const CONSECUTIVE_BONUS: i64 = 5;
+const WORD_START_BONUS: i64 = 8;
+const FIRST_CHAR_BONUS: i64 = 10;
+const SUBSTRING_BONUS: i64 = 20;
+
+/// Scores how well `pattern` matches `candidate` as a case-insensitive
+/// subsequence, or returns `None` if it does not match at all. Higher is
+/// better: runs of consecutive characters, matches at the start of words and
+/// plain substring matches score extra, and shorter candidates win ties.
+pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+    let mut total = 0;
+    let mut pattern_index = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for (index, c) in candidate_chars.iter().enumerate() {
+        if pattern_index == pattern.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(std::iter::once(pattern[pattern_index])) {
+            continue;
+        }
+
+        total += MATCH_SCORE;
+        if index == 0 {
+            total += FIRST_CHAR_BONUS;
+        }
+        if is_word_start(&candidate_chars, index) {
+            total += WORD_START_BONUS;
+        }
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            total += CONSECUTIVE_BONUS;
+        }
+
+        previous_match = Some(index);
+        pattern_index += 1;
+    }
+
+    if pattern_index < pattern.len() {
+        return None;
+    }
+
+    let needle: String = pattern.iter().collect();
+    if candidate.to_lowercase().contains(&needle) {
+        total += SUBSTRING_BONUS;
+    }
+
+    Some(total * 100 - candidate_chars.len() as i64)
+}
+
+/// Whether `index` starts a word: after a separator, or a lower-to-upper case
+/// change as in `camelCase`.
+fn is_word_start(chars: &[char], index: usize) -> bool {
+    let Some(previous) = index.checked_sub(1).map(|i| chars[i]) else {
+        return true;
+    };
+    !previous.is_alphanumeric() || (previous.is_lowercase() && chars[index].is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked<'a>(pattern: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+        let mut scored: Vec<(i64, &str)> = candidates
+            .iter()
+            .filter_map(|candidate| Some((score(pattern, candidate)?, *candidate)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        scored.into_iter().map(|(_, candidate)| candidate).collect()
+    }
+
+    #[test]
+    fn matches_subsequences_ignoring_case() {
+        assert_eq!(score("", "anything"), Some(0));
+        assert!(score("DOC", "documents").is_some());
+        assert!(score("dcm", "Documents").is_some());
+        assert_eq!(score("xyz", "Documents"), None);
+        assert_eq!(score("ba", "ab"), None);
+    }
+
+    #[test]
+    fn orders_matches() {
+        assert_eq!(
+            ranked(
+                "doc",
+                &["d-o-c", "my_documents", "Documents", "dock", "Desktop"]
+            ),
+            ["dock", "Documents", "my_documents", "d-o-c"]
+        );
+    }
+
+    #[test]
+    fn prefers_word_starts_and_runs() {
+        assert!(score("fb", "foo_bar") > score("fb", "fabric"));
+        assert!(score("fb", "fooBar") > score("fb", "fabric"));
+        assert!(score("abc", "xabcx") > score("abc", "xaxbxcx"));
+    }
+
+    #[test]
+    fn shorter_candidates_win_ties() {
+        assert!(score("src", "src") > score("src", "srcs"));
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{operations, recent};
+use gtk::prelude::*;
+use gtk::{Label, ListBox, Orientation, Window};
+use std::path::{Path, PathBuf};
+
+/// The content type GIO reports for `path`, e.g. `text/plain`.
+pub fn content_type(path: &Path) -> String {
+    gio::File::for_path(path)
+        .query_info(
+            gio::FILE_ATTRIBUTE_STANDARD_CONTENT_TYPE,
+            gio::FileQueryInfoFlags::NONE,
+            gio::Cancellable::NONE,
+        )
+        .ok()
+        .and_then(|info| info.content_type())
+        .map(|content_type| content_type.to_string())
+        .unwrap_or_else(|| gio::content_type_guess(Some(path), &[]).0.to_string())
+}
+
+/// Applications registered for `path`'s content type, the default first.
+pub fn applications_for(path: &Path) -> Vec<gio::AppInfo> {
+    let content_type = content_type(path);
+    let default = gio::AppInfo::default_for_type(&content_type, false);
+    let mut applications: Vec<gio::AppInfo> = gio::AppInfo::all_for_type(&content_type)
+        .into_iter()
+        .filter(|app| app.should_show())
+        .collect();
+    if let Some(default) = default {
+        applications.retain(|app| !app.equal(&default));
+        applications.insert(0, default);
+    }
+    applications
+}
+
+/// Looks up an application listed by `applications_for` by its desktop id.
+pub fn application_by_id(path: &Path, id: &str) -> Option<gio::AppInfo> {
+    applications_for(path)
+        .into_iter()
+        .find(|app| app.id().as_deref() == Some(id))
+}
+
+/// Opens `path` with the default application for its type, explaining in a
+/// dialog when there is none.
+pub fn open_default(parent: &impl IsA<Window>, path: &Path) {
+    let content_type = content_type(path);
+    match gio::AppInfo::default_for_type(&content_type, false) {
+        Some(app) => launch(parent, &app, &[path.to_path_buf()]),
+        None => operations::show_error_dialog(
+            parent,
+            &format!("Cannot open “{}”", display_name(path)),
+            &format!(
+                "There is no application installed for {} files. Use Open With to choose one.",
+                gio::content_type_get_description(&content_type)
+            ),
+        ),
+    }
+}
+
+/// Opens `paths` with `app` and records them as recently used.
+pub fn launch(parent: &impl IsA<Window>, app: &gio::AppInfo, paths: &[PathBuf]) {
+    let files: Vec<gio::File> = paths.iter().map(gio::File::for_path).collect();
+    let context = parent.as_ref().display().app_launch_context();
+    match app.launch(&files, Some(&context)) {
+        Ok(()) => {
+            for path in paths {
+                recent::record(path);
+            }
+        }
+        Err(e) => operations::show_error_dialog(
+            parent,
+            &format!("Could not start {}", app.display_name()),
+            &e.to_string(),
+        ),
+    }
+}
+
+/// Lets the user pick any application registered for `path`'s type, and
+/// optionally make it the default for that type.
+pub fn show_open_with_dialog(parent: &impl IsA<Window>, path: &Path) {
+    let content_type = content_type(path);
+    let applications = applications_for(path);
+
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Open With"),
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::USE_HEADER_BAR,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Open", gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_default_size(400, 450);
+
+    let content_area = dialog.content_area();
+    content_area.set_spacing(12);
+    content_area.set_margin_start(12);
+    content_area.set_margin_end(12);
+    content_area.set_margin_top(12);
+    content_area.set_margin_bottom(12);
+
+    let description = Label::new(Some(&format!(
+        "Choose an application to open “{}” ({})",
+        display_name(path),
+        gio::content_type_get_description(&content_type)
+    )));
+    description.set_halign(gtk::Align::Start);
+    description.set_wrap(true);
+    content_area.append(&description);
+
+    let list = ListBox::new();
+    list.set_selection_mode(gtk::SelectionMode::Browse);
+    list.add_css_class("boxed-list");
+    for app in &applications {
+        list.append(&create_application_row(app));
+    }
+
+    let placeholder = Label::new(Some("No applications found"));
+    placeholder.add_css_class("dim-label");
+    placeholder.set_margin_top(24);
+    placeholder.set_margin_bottom(24);
+    list.set_placeholder(Some(&placeholder));
+
+    let list_scroll = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .child(&list)
+        .build();
+    content_area.append(&list_scroll);
+
+    let default_check = gtk::CheckButton::with_label(&format!(
+        "Always use for {} files",
+        gio::content_type_get_description(&content_type)
+    ));
+    content_area.append(&default_check);
+
+    if let Some(row) = list.row_at_index(0) {
+        list.select_row(Some(&row));
+    }
+    dialog.set_response_sensitive(gtk::ResponseType::Accept, !applications.is_empty());
+
+    // Double-clicking an application opens with it right away
+    let dialog_clone = dialog.clone();
+    list.connect_row_activated(move |_, _| dialog_clone.response(gtk::ResponseType::Accept));
+
+    let parent = parent.as_ref().clone();
+    let path = path.to_path_buf();
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            let app = list
+                .selected_row()
+                .and_then(|row| applications.get(row.index() as usize));
+            if let Some(app) = app {
+                if default_check.is_active() {
+                    if let Err(e) = app.set_as_default_for_type(&content_type) {
+                        operations::show_error_dialog(
+                            &parent,
+                            &format!("Could not make {} the default", app.display_name()),
+                            e.message(),
+                        );
+                    }
+                }
+                launch(&parent, app, &[path.clone()]);
+            }
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+fn create_application_row(app: &gio::AppInfo) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Horizontal, 12);
+    row.set_margin_start(12);
+    row.set_margin_end(12);
+    row.set_margin_top(6);
+    row.set_margin_bottom(6);
+
+    let icon = match app.icon() {
+        Some(icon) => gtk::Image::from_gicon(&icon),
+        None => gtk::Image::from_icon_name("application-x-executable"),
+    };
+    icon.set_pixel_size(32);
+    row.append(&icon);
+
+    let label = Label::new(Some(&app.display_name()));
+    label.set_halign(gtk::Align::Start);
+    label.set_hexpand(true);
+    row.append(&label);
+
+    row
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
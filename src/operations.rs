@@ -97,8 +97,15 @@ pub fn show_new_file_dialog(parent: &impl IsA<Window>, current_path: &Path) {
     dialog.present();
 }
 
-#[allow(unused)]
-pub fn show_delete_confirmation(parent: &impl IsA<Window>, paths: &[std::path::PathBuf]) -> bool {
+/// Asks whether `paths` should be moved to the trash and calls `on_confirmed`
+/// if the user agrees.
+pub fn show_delete_confirmation<F>(
+    parent: &impl IsA<Window>,
+    paths: &[std::path::PathBuf],
+    on_confirmed: F,
+) where
+    F: FnOnce() + 'static,
+{
     let count = paths.len();
     let message = if count == 1 {
         format!(
@@ -117,15 +124,21 @@ pub fn show_delete_confirmation(parent: &impl IsA<Window>, paths: &[std::path::P
         &message,
     );
 
-    dialog.set_secondary_text(Some("This action cannot be undone."));
+    dialog.set_secondary_text(Some("The items will be moved to the trash."));
 
-    let _response = dialog.run_future();
-    dialog.close();
+    let on_confirmed = std::cell::Cell::new(Some(on_confirmed));
+    dialog.connect_response(move |dialog, response| {
+        dialog.close();
+        if response == gtk::ResponseType::Ok {
+            if let Some(on_confirmed) = on_confirmed.take() {
+                on_confirmed();
+            }
+        }
+    });
 
-    false // Placeholder - would need async handling
+    dialog.present();
 }
 
-#[allow(unused)]
 pub fn delete_files(paths: &[std::path::PathBuf]) -> Result<(), String> {
     for path in paths {
         if let Err(e) = trash::delete(path) {
@@ -135,7 +148,6 @@ pub fn delete_files(paths: &[std::path::PathBuf]) -> Result<(), String> {
     Ok(())
 }
 
-#[allow(unused)]
 pub fn copy_files(sources: &[std::path::PathBuf], destination: &Path) -> Result<(), String> {
     for source in sources {
         let file_name = source.file_name().ok_or("Invalid source path")?;
@@ -154,7 +166,6 @@ pub fn copy_files(sources: &[std::path::PathBuf], destination: &Path) -> Result<
     Ok(())
 }
 
-#[allow(unused)]
 pub fn move_files(sources: &[std::path::PathBuf], destination: &Path) -> Result<(), String> {
     for source in sources {
         let file_name = source.file_name().ok_or("Invalid source path")?;
@@ -167,6 +178,28 @@ pub fn move_files(sources: &[std::path::PathBuf], destination: &Path) -> Result<
     Ok(())
 }
 
+/// Creates a symbolic link to each of `targets` in `destination`, named
+/// "Link to name" (numbered if that name is taken).
+pub fn create_links(targets: &[std::path::PathBuf], destination: &Path) -> Result<(), String> {
+    for target in targets {
+        let file_name = target
+            .file_name()
+            .ok_or("Invalid source path")?
+            .to_string_lossy();
+        let mut link_path = destination.join(format!("Link to {}", file_name));
+        let mut number = 2;
+        while fs::symlink_metadata(&link_path).is_ok() {
+            link_path = destination.join(format!("Link to {} ({})", file_name, number));
+            number += 1;
+        }
+
+        if let Err(e) = std::os::unix::fs::symlink(target, &link_path) {
+            return Err(format!("Failed to create link: {}", e));
+        }
+    }
+    Ok(())
+}
+
 fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     fs::create_dir_all(dst)?;
 
@@ -186,7 +219,7 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-fn show_error_dialog(parent: &impl IsA<Window>, title: &str, message: &str) {
+pub fn show_error_dialog(parent: &impl IsA<Window>, title: &str, message: &str) {
     let dialog = gtk::MessageDialog::new(
         Some(parent),
         gtk::DialogFlags::MODAL,
@@ -0,0 +1,204 @@
+use gtk::prelude::*;
+use gtk::{Label, ListBox, Orientation, SearchEntry};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Most entries listed at once.
+const MAX_ITEMS: usize = 50;
+
+/// One entry in a palette.
+pub struct PaletteItem {
+    pub title: String,
+    pub subtitle: String,
+    pub icon_name: &'static str,
+    /// Keyboard shortcut shown at the end of the row, e.g. "Ctrl+N".
+    pub shortcut: Option<String>,
+}
+
+/// A modal popover with a search entry and a list of matches, shown above
+/// the file view. The owner supplies the items for the typed text and is
+/// told which one was chosen.
+#[derive(Clone)]
+pub struct Palette {
+    popover: gtk::Popover,
+    entry: SearchEntry,
+    list: ListBox,
+    items: Rc<RefCell<Option<Rc<dyn Fn(&str) -> Vec<PaletteItem>>>>>,
+    on_activated: Rc<RefCell<Option<Rc<dyn Fn(usize)>>>>,
+}
+
+impl Palette {
+    pub fn new(parent: &impl IsA<gtk::Widget>, placeholder: &str) -> Self {
+        let entry = SearchEntry::new();
+        entry.set_placeholder_text(Some(placeholder));
+
+        let list = ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::Browse);
+        list.add_css_class("navigation-sidebar");
+
+        let list_scroll = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .min_content_height(320)
+            .child(&list)
+            .build();
+
+        let content = gtk::Box::new(Orientation::Vertical, 6);
+        content.append(&entry);
+        content.append(&list_scroll);
+        content.set_size_request(520, -1);
+
+        let popover = gtk::Popover::new();
+        popover.set_child(Some(&content));
+        popover.set_has_arrow(false);
+        popover.set_position(gtk::PositionType::Bottom);
+        popover.set_parent(parent);
+
+        // The popover is not a real child of `parent`, so release it by hand
+        let popover_clone = popover.clone();
+        parent.connect_destroy(move |_| popover_clone.unparent());
+
+        let palette = Palette {
+            popover,
+            entry,
+            list,
+            items: Rc::new(RefCell::new(None)),
+            on_activated: Rc::new(RefCell::new(None)),
+        };
+
+        palette.setup_signals();
+
+        palette
+    }
+
+    fn setup_signals(&self) {
+        let palette = self.clone();
+        self.entry
+            .connect_search_changed(move |_| palette.refresh());
+
+        let palette = self.clone();
+        self.entry.connect_activate(move |_| {
+            if let Some(row) = palette.list.selected_row() {
+                palette.activate(row.index());
+            }
+        });
+
+        let popover = self.popover.clone();
+        self.entry.connect_stop_search(move |_| popover.popdown());
+
+        // Up and Down move through the list while typing
+        let key_controller = gtk::EventControllerKey::new();
+        let list = self.list.clone();
+        key_controller.connect_key_pressed(move |_, key, _, _| {
+            let offset = match key {
+                gdk::Key::Down => 1,
+                gdk::Key::Up => -1,
+                _ => return glib::Propagation::Proceed,
+            };
+            let index = list.selected_row().map(|row| row.index()).unwrap_or(-1) + offset;
+            if let Some(row) = list.row_at_index(index) {
+                list.select_row(Some(&row));
+                row.grab_focus();
+            }
+            glib::Propagation::Stop
+        });
+        self.entry.add_controller(key_controller);
+
+        let palette = self.clone();
+        self.list
+            .connect_row_activated(move |_, row| palette.activate(row.index()));
+    }
+
+    /// Clears the entry and shows the palette.
+    pub fn popup(&self) {
+        self.entry.set_text("");
+        self.refresh();
+
+        // Centered at the top of the parent
+        if let Some(parent) = self.popover.parent() {
+            let rect = gdk::Rectangle::new(parent.width() / 2, 0, 1, 1);
+            self.popover.set_pointing_to(Some(&rect));
+        }
+        self.popover.popup();
+        self.entry.grab_focus();
+    }
+
+    fn refresh(&self) {
+        while let Some(child) = self.list.first_child() {
+            self.list.remove(&child);
+        }
+
+        let Some(items) = self.items.borrow().clone() else {
+            return;
+        };
+        for item in items(&self.entry.text()).into_iter().take(MAX_ITEMS) {
+            self.list.append(&create_row(&item));
+        }
+
+        if let Some(row) = self.list.row_at_index(0) {
+            self.list.select_row(Some(&row));
+        }
+    }
+
+    fn activate(&self, index: i32) {
+        self.popover.popdown();
+        if let Some(callback) = self.on_activated.borrow().as_ref() {
+            callback(index as usize);
+        }
+    }
+
+    /// Sets the function listing the items that match the typed text, best
+    /// first.
+    pub fn connect_items<F>(&self, callback: F)
+    where
+        F: Fn(&str) -> Vec<PaletteItem> + 'static,
+    {
+        *self.items.borrow_mut() = Some(Rc::new(callback));
+    }
+
+    /// Called with the index of the chosen item in the last list returned by
+    /// the `connect_items` function.
+    pub fn connect_activated<F>(&self, callback: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        *self.on_activated.borrow_mut() = Some(Rc::new(callback));
+    }
+}
+
+fn create_row(item: &PaletteItem) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Horizontal, 12);
+    row.set_margin_start(6);
+    row.set_margin_end(6);
+    row.set_margin_top(4);
+    row.set_margin_bottom(4);
+
+    let icon = gtk::Image::from_icon_name(item.icon_name);
+    icon.set_pixel_size(16);
+    row.append(&icon);
+
+    let text_box = gtk::Box::new(Orientation::Vertical, 2);
+    text_box.set_hexpand(true);
+
+    let title_label = Label::new(Some(&item.title));
+    title_label.set_halign(gtk::Align::Start);
+    title_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+    text_box.append(&title_label);
+
+    if !item.subtitle.is_empty() {
+        let subtitle_label = Label::new(Some(&item.subtitle));
+        subtitle_label.set_halign(gtk::Align::Start);
+        subtitle_label.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+        subtitle_label.add_css_class("dim-label");
+        subtitle_label.add_css_class("caption");
+        text_box.append(&subtitle_label);
+    }
+    row.append(&text_box);
+
+    if let Some(shortcut) = &item.shortcut {
+        let shortcut_label = Label::new(Some(shortcut));
+        shortcut_label.add_css_class("dim-label");
+        row.append(&shortcut_label);
+    }
+
+    row
+}
@@ -1,9 +1,14 @@
+use crate::file_info::SizeUnits;
+use crate::settings::{DefaultView, Settings, SettingsStore};
 use gtk::prelude::*;
 use gtk::{
-    Box, ComboBoxText, Dialog, DialogFlags, Label, Orientation, ResponseType, Switch, Window,
+    Box, ComboBoxText, Dialog, DialogFlags, Entry, FileChooserAction, FileChooserDialog, Label,
+    ListBox, Orientation, ResponseType, Switch, Window,
 };
 
-pub fn show_preferences_dialog(parent: &impl IsA<Window>) {
+pub fn show_preferences_dialog(parent: &impl IsA<Window>, settings: &SettingsStore) {
+    let current = settings.get();
+
     let dialog = Dialog::with_buttons(
         Some("Preferences"),
         Some(parent),
@@ -11,7 +16,7 @@ pub fn show_preferences_dialog(parent: &impl IsA<Window>) {
         &[("Close", ResponseType::Close)],
     );
 
-    dialog.set_default_size(500, 400);
+    dialog.set_default_size(500, 600);
 
     let content_area = dialog.content_area();
     content_area.set_margin_start(12);
@@ -29,20 +34,82 @@ pub fn show_preferences_dialog(parent: &impl IsA<Window>) {
         "Display files and folders that start with a dot",
     );
     let show_hidden_switch = Switch::new();
+    show_hidden_switch.set_active(current.show_hidden);
+    bind_switch(&show_hidden_switch, settings, |s, active| {
+        s.show_hidden = active
+    });
     show_hidden_switch.set_valign(gtk::Align::Center);
     show_hidden_row.append(&show_hidden_switch);
     view_section.append(&show_hidden_row);
 
+    let hide_backups_row = create_preference_row(
+        "Treat backup files as hidden",
+        "Hide files ending in ~ or .bak along with hidden files",
+    );
+    let hide_backups_switch = Switch::new();
+    hide_backups_switch.set_active(current.hide_backup_files);
+    bind_switch(&hide_backups_switch, settings, |s, active| {
+        s.hide_backup_files = active
+    });
+    hide_backups_switch.set_valign(gtk::Align::Center);
+    hide_backups_row.append(&hide_backups_switch);
+    view_section.append(&hide_backups_row);
+
     let default_view_row =
         create_preference_row("Default view", "Choose the default view mode for folders");
     let default_view_combo = ComboBoxText::new();
     default_view_combo.append_text("Grid View");
     default_view_combo.append_text("List View");
-    default_view_combo.set_active(Some(0));
+    default_view_combo.set_active(Some(match current.default_view {
+        DefaultView::Grid => 0,
+        DefaultView::List => 1,
+    }));
+    let settings_clone = settings.clone();
+    default_view_combo.connect_changed(move |combo| {
+        let default_view = match combo.active() {
+            Some(1) => DefaultView::List,
+            _ => DefaultView::Grid,
+        };
+        settings_clone.update(|s| s.default_view = default_view);
+    });
     default_view_combo.set_valign(gtk::Align::Center);
     default_view_row.append(&default_view_combo);
     view_section.append(&default_view_row);
 
+    let size_units_row =
+        create_preference_row("Size units", "Count sizes in thousands or in 1024s");
+    let size_units_combo = ComboBoxText::new();
+    size_units_combo.append_text("Decimal (KB, MB)");
+    size_units_combo.append_text("Binary (KiB, MiB)");
+    size_units_combo.set_active(Some(match current.size_units {
+        SizeUnits::Decimal => 0,
+        SizeUnits::Binary => 1,
+    }));
+    let settings_clone = settings.clone();
+    size_units_combo.connect_changed(move |combo| {
+        let size_units = match combo.active() {
+            Some(1) => SizeUnits::Binary,
+            _ => SizeUnits::Decimal,
+        };
+        settings_clone.update(|s| s.size_units = size_units);
+    });
+    size_units_combo.set_valign(gtk::Align::Center);
+    size_units_row.append(&size_units_combo);
+    view_section.append(&size_units_row);
+
+    let allocated_size_row = create_preference_row(
+        "Show size on disk",
+        "Show the space files take on disk instead of the size of their contents",
+    );
+    let allocated_size_switch = Switch::new();
+    allocated_size_switch.set_active(current.show_allocated_size);
+    bind_switch(&allocated_size_switch, settings, |s, active| {
+        s.show_allocated_size = active
+    });
+    allocated_size_switch.set_valign(gtk::Align::Center);
+    allocated_size_row.append(&allocated_size_switch);
+    view_section.append(&allocated_size_row);
+
     // Behavior preferences
     let behavior_section = create_section("Behavior");
     content_area.append(&behavior_section);
@@ -52,6 +119,10 @@ pub fn show_preferences_dialog(parent: &impl IsA<Window>) {
         "Open files and folders with a single click",
     );
     let single_click_switch = Switch::new();
+    single_click_switch.set_active(current.single_click);
+    bind_switch(&single_click_switch, settings, |s, active| {
+        s.single_click = active
+    });
     single_click_switch.set_valign(gtk::Align::Center);
     single_click_row.append(&single_click_switch);
     behavior_section.append(&single_click_row);
@@ -61,7 +132,10 @@ pub fn show_preferences_dialog(parent: &impl IsA<Window>) {
         "Show confirmation dialog before deleting files",
     );
     let confirm_delete_switch = Switch::new();
-    confirm_delete_switch.set_active(true);
+    confirm_delete_switch.set_active(current.confirm_delete);
+    bind_switch(&confirm_delete_switch, settings, |s, active| {
+        s.confirm_delete = active
+    });
     confirm_delete_switch.set_valign(gtk::Align::Center);
     confirm_delete_row.append(&confirm_delete_switch);
     behavior_section.append(&confirm_delete_row);
@@ -75,18 +149,198 @@ pub fn show_preferences_dialog(parent: &impl IsA<Window>) {
         "Generate and display thumbnails for image files",
     );
     let thumbnail_switch = Switch::new();
-    thumbnail_switch.set_active(true);
+    thumbnail_switch.set_active(current.show_thumbnails);
+    bind_switch(&thumbnail_switch, settings, |s, active| {
+        s.show_thumbnails = active
+    });
     thumbnail_switch.set_valign(gtk::Align::Center);
     thumbnail_row.append(&thumbnail_switch);
     performance_section.append(&thumbnail_row);
 
-    dialog.connect_response(|dialog, _| {
+    let folder_sizes_row = create_preference_row(
+        "Calculate folder sizes",
+        "Add up the contents of folders in the background to show and sort by their size",
+    );
+    let folder_sizes_switch = Switch::new();
+    folder_sizes_switch.set_active(current.folder_sizes);
+    bind_switch(&folder_sizes_switch, settings, |s, active| {
+        s.folder_sizes = active
+    });
+    folder_sizes_switch.set_valign(gtk::Align::Center);
+    folder_sizes_row.append(&folder_sizes_switch);
+    performance_section.append(&folder_sizes_row);
+
+    // Search preferences
+    let search_section = create_section("Search");
+    content_area.append(&search_section);
+
+    let index_row = create_preference_row(
+        "Index files for instant search",
+        "Keep a list of file names in the folders below to search them instantly",
+    );
+    let index_switch = Switch::new();
+    index_switch.set_active(current.index_enabled);
+    bind_switch(&index_switch, settings, |s, active| {
+        s.index_enabled = active
+    });
+    index_switch.set_valign(gtk::Align::Center);
+    index_row.append(&index_switch);
+    search_section.append(&index_row);
+
+    let roots_row = create_preference_row("Indexed folders", "Folders searched through the index");
+    let add_root_button = gtk::Button::with_label("Add Folder…");
+    add_root_button.set_valign(gtk::Align::Center);
+    roots_row.append(&add_root_button);
+    search_section.append(&roots_row);
+
+    let roots_list = ListBox::new();
+    roots_list.set_selection_mode(gtk::SelectionMode::None);
+    roots_list.add_css_class("boxed-list");
+    roots_list.set_margin_start(12);
+    roots_list.set_margin_end(12);
+    populate_indexed_roots(&roots_list, settings);
+    search_section.append(&roots_list);
+
+    let parent_dialog = dialog.clone();
+    let settings_clone = settings.clone();
+    let roots_list_clone = roots_list.clone();
+    add_root_button.connect_clicked(move |_| {
+        let settings = settings_clone.clone();
+        let roots_list = roots_list_clone.clone();
+        choose_folder(&parent_dialog, move |folder| {
+            settings.update(|s| {
+                if !s.indexed_roots.contains(&folder) {
+                    s.indexed_roots.push(folder);
+                }
+            });
+            populate_indexed_roots(&roots_list, &settings);
+        });
+    });
+
+    let exclusions_row = create_preference_row(
+        "Excluded from the index",
+        "Comma-separated names such as node_modules or *.o, or folders such as ~/Downloads",
+    );
+    search_section.append(&exclusions_row);
+    let exclusions_entry = Entry::new();
+    exclusions_entry.set_text(&current.index_exclusions.join(", "));
+    exclusions_entry.set_margin_start(12);
+    exclusions_entry.set_margin_end(12);
+    search_section.append(&exclusions_entry);
+
+    // Rebuilding the index on every keystroke would be wasteful, so the
+    // exclusions are applied on Enter and when the dialog closes
+    let settings_clone = settings.clone();
+    exclusions_entry.connect_activate(move |entry| {
+        apply_index_exclusions(&settings_clone, &entry.text());
+    });
+
+    for widget in [
+        roots_row.upcast_ref::<gtk::Widget>(),
+        roots_list.upcast_ref(),
+        exclusions_row.upcast_ref(),
+        exclusions_entry.upcast_ref(),
+    ] {
+        index_switch
+            .bind_property("active", widget, "sensitive")
+            .sync_create()
+            .build();
+    }
+
+    let settings_clone = settings.clone();
+    dialog.connect_response(move |dialog, _| {
+        apply_index_exclusions(&settings_clone, &exclusions_entry.text());
         dialog.close();
     });
 
     dialog.present();
 }
 
+/// Writes the switch state into the settings whenever it is toggled.
+fn bind_switch<F>(switch: &Switch, settings: &SettingsStore, apply: F)
+where
+    F: Fn(&mut Settings, bool) + 'static,
+{
+    let settings = settings.clone();
+    switch.connect_active_notify(move |switch| {
+        let active = switch.is_active();
+        settings.update(|s| apply(s, active));
+    });
+}
+
+/// Lists the indexed folders, each with a button to stop indexing it.
+fn populate_indexed_roots(list: &ListBox, settings: &SettingsStore) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+
+    for root in settings.get().indexed_roots {
+        let row = Box::new(Orientation::Horizontal, 12);
+        row.set_margin_start(12);
+        row.set_margin_end(12);
+        row.set_margin_top(6);
+        row.set_margin_bottom(6);
+
+        let label = Label::new(Some(&root.to_string_lossy()));
+        label.set_halign(gtk::Align::Start);
+        label.set_hexpand(true);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+        row.append(&label);
+
+        let remove_button = gtk::Button::from_icon_name("list-remove-symbolic");
+        remove_button.add_css_class("flat");
+        remove_button.set_tooltip_text(Some("Stop indexing this folder"));
+        let settings = settings.clone();
+        let list_weak = list.downgrade();
+        remove_button.connect_clicked(move |_| {
+            settings.update(|s| s.indexed_roots.retain(|r| *r != root));
+            if let Some(list) = list_weak.upgrade() {
+                populate_indexed_roots(&list, &settings);
+            }
+        });
+        row.append(&remove_button);
+
+        list.append(&row);
+    }
+}
+
+fn apply_index_exclusions(settings: &SettingsStore, text: &str) {
+    let exclusions: Vec<String> = text
+        .split(',')
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    settings.update(|s| s.index_exclusions = exclusions);
+}
+
+/// Lets the user pick a folder and passes it to `on_chosen`.
+fn choose_folder<F>(parent: &impl IsA<Window>, on_chosen: F)
+where
+    F: Fn(std::path::PathBuf) + 'static,
+{
+    let chooser = FileChooserDialog::new(
+        Some("Choose a Folder to Index"),
+        Some(parent),
+        FileChooserAction::SelectFolder,
+        &[
+            ("Cancel", ResponseType::Cancel),
+            ("Add", ResponseType::Accept),
+        ],
+    );
+    chooser.set_modal(true);
+
+    chooser.connect_response(move |chooser, response| {
+        if response == ResponseType::Accept {
+            if let Some(folder) = chooser.file().and_then(|file| file.path()) {
+                on_chosen(folder);
+            }
+        }
+        chooser.close();
+    });
+
+    chooser.present();
+}
+
 fn create_section(title: &str) -> Box {
     let section = Box::new(Orientation::Vertical, 6);
 
@@ -0,0 +1,499 @@
+use crate::file_info::{self, FileInfo, SizeUnits};
+use crate::folder_size::FolderSizeCache;
+use crate::operations;
+use crate::services::{AppServices, JobQueue};
+use crate::users;
+use gtk::prelude::*;
+use gtk::{Label, Orientation, Window};
+use std::cell::Cell;
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Permission classes in `rwx` order of the mode bits.
+const CLASSES: [(&str, u32); 3] = [("Owner", 6), ("Group", 3), ("Others", 0)];
+const ACCESS: [(&str, u32); 3] = [("Read", 0o4), ("Write", 0o2), ("Execute", 0o1)];
+
+/// Shows the properties of `path`. `on_changed` is called after its
+/// permissions or group were changed.
+pub fn show_properties_dialog<F>(
+    parent: &impl IsA<Window>,
+    services: &AppServices,
+    path: &Path,
+    on_changed: F,
+) where
+    F: Fn() + 'static,
+{
+    let file = match FileInfo::from_path(path) {
+        Ok(mut file) => {
+            file.sniff();
+            file
+        }
+        Err(e) => {
+            operations::show_error_dialog(
+                parent,
+                &format!("Cannot show properties of {}", path.display()),
+                &e.to_string(),
+            );
+            return;
+        }
+    };
+
+    let dialog = gtk::Dialog::with_buttons(
+        Some(&format!("{} Properties", file.name)),
+        Some(parent),
+        gtk::DialogFlags::USE_HEADER_BAR,
+        &[("Close", gtk::ResponseType::Close)],
+    );
+    dialog.set_default_size(440, -1);
+    dialog.connect_response(|dialog, _| dialog.close());
+
+    let notebook = gtk::Notebook::new();
+    notebook.append_page(
+        &basic_page(&dialog, services, &file),
+        Some(&Label::new(Some("Basic"))),
+    );
+    notebook.append_page(
+        &permissions_page(&services.jobs, &file, Rc::new(on_changed)),
+        Some(&Label::new(Some("Permissions"))),
+    );
+    dialog.content_area().append(&notebook);
+
+    dialog.present();
+}
+
+fn basic_page(dialog: &gtk::Dialog, services: &AppServices, file: &FileInfo) -> gtk::Grid {
+    let units = services.settings.get().size_units;
+    let grid = property_grid();
+    let mut row = 0;
+
+    add_row(&grid, &mut row, "Name", &file.name);
+    add_row(&grid, &mut row, "Type", &file.type_description());
+    add_row(&grid, &mut row, "MIME Type", &file.mime_type);
+    if let Some(parent) = file.path.parent() {
+        add_row(&grid, &mut row, "Location", &parent.to_string_lossy());
+    }
+    if let Some(target) = &file.link_target {
+        let text = if file.is_dangling {
+            format!("{} (broken)", target.display())
+        } else {
+            target.to_string_lossy().to_string()
+        };
+        add_row(&grid, &mut row, "Link Target", &text);
+    }
+
+    if file.is_dir {
+        let size_label = add_row(&grid, &mut row, "Size", "Calculating…");
+        let allocated_label = add_row(&grid, &mut row, "On Disk", "Calculating…");
+        measure_in_background(
+            dialog,
+            &services.folder_sizes,
+            &file.path,
+            units,
+            &size_label,
+            &allocated_label,
+        );
+    } else {
+        add_row(&grid, &mut row, "Size", &describe_bytes(file.size, units));
+        if let Some(allocated_size) = file.allocated_size {
+            add_row(
+                &grid,
+                &mut row,
+                "On Disk",
+                &describe_bytes(allocated_size, units),
+            );
+        }
+    }
+
+    add_row(
+        &grid,
+        &mut row,
+        "Modified",
+        &file_info::format_time(file.modified),
+    );
+    if let Some(unix) = &file.unix {
+        add_row(
+            &grid,
+            &mut row,
+            "Accessed",
+            &file_info::format_time(unix.accessed),
+        );
+        if let Some(created) = unix.created {
+            add_row(&grid, &mut row, "Created", &file_info::format_time(created));
+        }
+    }
+
+    grid
+}
+
+/// Fills in the labels with the folder's total sizes once they have been
+/// added up. Closing the dialog stops the count.
+fn measure_in_background(
+    dialog: &gtk::Dialog,
+    cache: &FolderSizeCache,
+    folder: &Path,
+    units: SizeUnits,
+    size_label: &Label,
+    allocated_label: &Label,
+) {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let worker_cancelled = cancelled.clone();
+    let cache = cache.clone();
+    let folder = folder.to_path_buf();
+    let size_label_weak = size_label.downgrade();
+    let allocated_label_weak = allocated_label.downgrade();
+    glib::spawn_future_local(async move {
+        let size = gio::spawn_blocking(move || cache.measure(&folder, &worker_cancelled)).await;
+        let (Ok(Some(size)), Some(size_label), Some(allocated_label)) = (
+            size,
+            size_label_weak.upgrade(),
+            allocated_label_weak.upgrade(),
+        ) else {
+            return;
+        };
+        size_label.set_text(&format!(
+            "{}, {}",
+            size.describe_items(),
+            describe_bytes(size.bytes, units)
+        ));
+        allocated_label.set_text(&describe_bytes(size.allocated, units));
+    });
+    dialog.connect_destroy(move |_| cancelled.store(true, Ordering::Relaxed));
+}
+
+fn permissions_page(jobs: &JobQueue, file: &FileInfo, on_changed: Rc<dyn Fn()>) -> gtk::Box {
+    let page = gtk::Box::new(Orientation::Vertical, 12);
+    page.set_margin_start(18);
+    page.set_margin_end(18);
+    page.set_margin_top(18);
+    page.set_margin_bottom(18);
+
+    // Permissions of the link target, as chmod would change
+    let metadata = match fs::metadata(&file.path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            page.append(&Label::new(Some(&e.to_string())));
+            return page;
+        }
+    };
+    let is_owner = users::current_uid() == metadata.uid();
+    let mode = Rc::new(Cell::new(metadata.mode() & 0o7777));
+    let gid = Rc::new(Cell::new(metadata.gid()));
+
+    let grid = property_grid();
+    grid.set_margin_start(0);
+    grid.set_margin_end(0);
+    grid.set_margin_top(0);
+    grid.set_margin_bottom(0);
+    let mut row = 0;
+    add_row(&grid, &mut row, "Owner", &users::user_name(metadata.uid()));
+
+    // Group, changeable by the owner among the groups they belong to. The
+    // current group is listed even when it is not one of them, e.g. for
+    // files extracted from an archive.
+    if is_owner {
+        let mut groups = users::current_user_groups();
+        if !groups.iter().any(|(id, _)| *id == metadata.gid()) {
+            groups.push((metadata.gid(), users::group_name(metadata.gid())));
+        }
+        let names: Vec<&str> = groups.iter().map(|(_, name)| name.as_str()).collect();
+        let group_dropdown = gtk::DropDown::from_strings(&names);
+        if let Some(index) = groups.iter().position(|(id, _)| *id == metadata.gid()) {
+            group_dropdown.set_selected(index as u32);
+        }
+        group_dropdown.set_halign(gtk::Align::Start);
+        add_widget_row(&grid, &mut row, "Group", &group_dropdown);
+
+        let path = file.path.clone();
+        let gid = gid.clone();
+        let on_changed = on_changed.clone();
+        group_dropdown.connect_selected_notify(move |dropdown| {
+            let Some((new_gid, _)) = groups.get(dropdown.selected() as usize) else {
+                return;
+            };
+            if *new_gid == gid.get() {
+                return;
+            }
+            match std::os::unix::fs::chown(&path, None, Some(*new_gid)) {
+                Ok(()) => {
+                    gid.set(*new_gid);
+                    on_changed();
+                }
+                Err(e) => {
+                    show_error(dropdown, "Failed to change group", &e.to_string());
+                    // Go back to the group the file still has
+                    if let Some(index) = groups.iter().position(|(id, _)| *id == gid.get()) {
+                        dropdown.set_selected(index as u32);
+                    }
+                }
+            }
+        });
+    } else {
+        add_row(&grid, &mut row, "Group", &users::group_name(metadata.gid()));
+    }
+    page.append(&grid);
+
+    // Read/write/execute for each class
+    let access_grid = gtk::Grid::new();
+    access_grid.set_row_spacing(6);
+    access_grid.set_column_spacing(18);
+    for (column, (name, _)) in ACCESS.iter().enumerate() {
+        let header = Label::new(Some(name));
+        header.add_css_class("dim-label");
+        access_grid.attach(&header, column as i32 + 1, 0, 1, 1);
+    }
+
+    let updating = Rc::new(Cell::new(false));
+    let mut checks: Vec<(gtk::CheckButton, u32)> = Vec::new();
+    for (class_row, (class_name, shift)) in CLASSES.iter().enumerate() {
+        let label = Label::new(Some(class_name));
+        label.set_halign(gtk::Align::End);
+        access_grid.attach(&label, 0, class_row as i32 + 1, 1, 1);
+
+        for (column, (_, bit)) in ACCESS.iter().enumerate() {
+            let mask = bit << shift;
+            let check = gtk::CheckButton::new();
+            check.set_halign(gtk::Align::Center);
+            check.set_active(mode.get() & mask != 0);
+            check.set_sensitive(is_owner);
+            access_grid.attach(&check, column as i32 + 1, class_row as i32 + 1, 1, 1);
+            checks.push((check, mask));
+        }
+    }
+    page.append(&access_grid);
+
+    // Shortcut for scripts and programs: execute wherever read is allowed
+    let executable_check = gtk::CheckButton::with_label("Allow executing file as program");
+    executable_check.set_active(mode.get() & 0o100 != 0);
+    executable_check.set_sensitive(is_owner);
+    executable_check.set_visible(!file.is_dir);
+    page.append(&executable_check);
+
+    let checks = Rc::new(checks);
+    let apply_mode = {
+        let path = file.path.clone();
+        let mode = mode.clone();
+        let checks = checks.clone();
+        let executable_check = executable_check.clone();
+        let updating = updating.clone();
+        let on_changed = on_changed.clone();
+        Rc::new(move |new_mode: u32| {
+            match fs::set_permissions(&path, fs::Permissions::from_mode(new_mode)) {
+                Ok(()) => {
+                    mode.set(new_mode);
+                    on_changed();
+                }
+                Err(e) => show_error(
+                    &executable_check,
+                    "Failed to change permissions",
+                    &e.to_string(),
+                ),
+            }
+
+            // Reflect what was actually applied
+            updating.set(true);
+            for (check, mask) in checks.iter() {
+                check.set_active(mode.get() & mask != 0);
+            }
+            executable_check.set_active(mode.get() & 0o100 != 0);
+            updating.set(false);
+        })
+    };
+
+    for (check, mask) in checks.iter() {
+        let mask = *mask;
+        let mode = mode.clone();
+        let updating = updating.clone();
+        let apply_mode = apply_mode.clone();
+        check.connect_toggled(move |check| {
+            if updating.get() {
+                return;
+            }
+            let new_mode = if check.is_active() {
+                mode.get() | mask
+            } else {
+                mode.get() & !mask
+            };
+            apply_mode(new_mode);
+        });
+    }
+
+    let mode_clone = mode.clone();
+    let updating_clone = updating.clone();
+    executable_check.connect_toggled(move |check| {
+        if updating_clone.get() {
+            return;
+        }
+        let mode = mode_clone.get();
+        let new_mode = if check.is_active() {
+            // r bits shifted down onto the x bits, always including the owner
+            mode | ((mode & 0o444) >> 2) | 0o100
+        } else {
+            mode & !0o111
+        };
+        apply_mode(new_mode);
+    });
+
+    // Folders can pass their permissions and group on to everything inside
+    if file.is_dir && is_owner {
+        let apply_button = gtk::Button::with_label("Apply Permissions to Enclosed Files");
+        apply_button.set_halign(gtk::Align::Start);
+        apply_button.set_tooltip_text(Some(
+            "Folders get these permissions; files get them too, but can only lose execute",
+        ));
+        let jobs = jobs.clone();
+        let folder = file.path.clone();
+        apply_button.connect_clicked(move |button| {
+            let folder = folder.clone();
+            let mode = mode.get();
+            let gid = gid.get();
+            let button = button.clone();
+            let on_changed = on_changed.clone();
+            button.set_sensitive(false);
+            jobs.push(
+                move || apply_recursively(&folder, mode, gid),
+                move |result| {
+                    button.set_sensitive(true);
+                    if let Err(e) = result {
+                        show_error(&button, "Failed to apply permissions", &e);
+                    }
+                    on_changed();
+                },
+            );
+        });
+        page.append(&apply_button);
+    }
+
+    if !is_owner {
+        let note = Label::new(Some(
+            "You are not the owner, so you cannot change these permissions.",
+        ));
+        note.set_wrap(true);
+        note.set_halign(gtk::Align::Start);
+        note.add_css_class("dim-label");
+        page.append(&note);
+    }
+
+    page
+}
+
+/// Sets the mode and group of everything below `folder`. Symbolic links and
+/// other filesystems are left alone. Files only keep execute permission
+/// where they had it and `mode` grants it.
+fn apply_recursively(folder: &Path, mode: u32, gid: u32) -> Result<(), String> {
+    let device = fs::metadata(folder).map_err(|e| e.to_string())?.dev();
+    let mut failures: Vec<PathBuf> = Vec::new();
+    apply_to_children(folder, mode, gid, device, &mut failures);
+
+    match failures.as_slice() {
+        [] => Ok(()),
+        [path] => Err(format!("Could not change {}", path.display())),
+        [path, rest @ ..] => Err(format!(
+            "Could not change {} and {} other items",
+            path.display(),
+            rest.len()
+        )),
+    }
+}
+
+/// Changes the entries of `folder`, each folder after its contents: its new
+/// mode may no longer let us list or enter it. A folder that can't be listed
+/// counts as a failure, since what's inside stays unchanged.
+fn apply_to_children(folder: &Path, mode: u32, gid: u32, device: u64, failures: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(folder) else {
+        failures.push(folder.to_path_buf());
+        return;
+    };
+    for entry in entries {
+        let Ok(entry) = entry else {
+            failures.push(folder.to_path_buf());
+            continue;
+        };
+        let path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            failures.push(path);
+            continue;
+        };
+        if metadata.file_type().is_symlink() || metadata.dev() != device {
+            continue;
+        }
+
+        let mut failed = false;
+        let new_mode = if metadata.is_dir() {
+            // Let ourselves in first, in case only the new mode would
+            let entered = metadata.mode() & 0o500 == 0o500
+                || fs::set_permissions(
+                    &path,
+                    fs::Permissions::from_mode((metadata.mode() & 0o7777) | 0o700),
+                )
+                .is_ok();
+            if entered {
+                apply_to_children(&path, mode, gid, device, failures);
+            } else {
+                failed = true;
+            }
+            mode & 0o7777
+        } else {
+            // Set-user-ID and the other special bits don't carry over to
+            // files, and execute stays only where both allow it
+            (mode & 0o666) | (metadata.mode() & mode & 0o111)
+        };
+        let result = fs::set_permissions(&path, fs::Permissions::from_mode(new_mode))
+            .and_then(|_| std::os::unix::fs::chown(&path, None, Some(gid)));
+        if failed || result.is_err() {
+            failures.push(path);
+        }
+    }
+}
+
+fn property_grid() -> gtk::Grid {
+    let grid = gtk::Grid::new();
+    grid.set_row_spacing(8);
+    grid.set_column_spacing(12);
+    grid.set_margin_start(18);
+    grid.set_margin_end(18);
+    grid.set_margin_top(18);
+    grid.set_margin_bottom(18);
+    grid
+}
+
+/// Appends a "Name: value" row and returns the value label.
+fn add_row(grid: &gtk::Grid, row: &mut i32, name: &str, value: &str) -> Label {
+    let value_label = Label::new(Some(value));
+    value_label.set_halign(gtk::Align::Start);
+    value_label.set_selectable(true);
+    value_label.set_wrap(true);
+    value_label.set_wrap_mode(gtk::pango::WrapMode::WordChar);
+    value_label.set_xalign(0.0);
+    add_widget_row(grid, row, name, &value_label);
+    value_label
+}
+
+fn add_widget_row(grid: &gtk::Grid, row: &mut i32, name: &str, widget: &impl IsA<gtk::Widget>) {
+    let name_label = Label::new(Some(name));
+    name_label.set_halign(gtk::Align::End);
+    name_label.set_valign(gtk::Align::Start);
+    name_label.add_css_class("dim-label");
+    grid.attach(&name_label, 0, *row, 1, 1);
+    grid.attach(widget, 1, *row, 1, 1);
+    *row += 1;
+}
+
+/// "1.2 MB (1,234,567 bytes)"
+fn describe_bytes(bytes: u64, units: SizeUnits) -> String {
+    format!(
+        "{} ({})",
+        file_info::format_bytes(bytes, units),
+        file_info::format_byte_count(bytes)
+    )
+}
+
+/// Shows an error over the window containing `widget`.
+fn show_error(widget: &impl IsA<gtk::Widget>, title: &str, message: &str) {
+    if let Some(window) = widget.root().and_downcast::<Window>() {
+        operations::show_error_dialog(&window, title, message);
+    }
+}
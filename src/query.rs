@@ -0,0 +1,560 @@
+use crate::file_info::FileInfo;
+use crate::users;
+use chrono::{Local, NaiveDate, TimeZone};
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::time::{Duration, SystemTime};
+
+const FILTER_NAMES: &str = "type, size, modified, owner, ext";
+
+/// A parsed search such as `report type:document size:>10M modified:<7d`:
+/// free text matched against names, plus structured filters.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub text: String,
+    pub filters: Vec<Filter>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    Kind(FileKind),
+    Size(Comparison, u64),
+    /// Compares the modification time against a point in time.
+    Modified(Comparison, SystemTime),
+    Owner(u32),
+    Extension(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileKind {
+    File,
+    Folder,
+    Image,
+    Audio,
+    Video,
+    Document,
+    Archive,
+    Code,
+    Text,
+}
+
+/// Why a filter in the query could not be understood.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    pub token: String,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.token, self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl Query {
+    /// Parses `input` into free text and filters. Words of the form `key:value`
+    /// are filters; quoting a word (`"12:30"`) keeps it as plain text.
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut words = Vec::new();
+        let mut filters = Vec::new();
+
+        for (token, quoted) in tokenize(input) {
+            match token.split_once(':') {
+                Some((key, value)) if !quoted && is_filter_key(key) => {
+                    filters.push(parse_filter(&token, key, value)?);
+                }
+                _ => words.push(token),
+            }
+        }
+
+        Ok(Query {
+            text: words.join(" "),
+            filters,
+        })
+    }
+
+    /// Whether `file` passes every filter. The free text is matched separately.
+    pub fn matches(&self, file: &FileInfo) -> bool {
+        self.filters.iter().all(|filter| filter.matches(file))
+    }
+
+    /// Whether matching depends on the MIME type, so files whose names don't
+    /// settle it must be sniffed first.
+    pub fn needs_mime_type(&self) -> bool {
+        self.filters
+            .iter()
+            .any(|filter| matches!(filter, Filter::Kind(_)))
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, file: &FileInfo) -> bool {
+        match self {
+            Filter::Kind(kind) => kind.matches(file),
+            Filter::Size(comparison, size) => !file.is_dir && comparison.test(file.size, *size),
+            Filter::Modified(comparison, time) => comparison.test(file.modified, *time),
+            Filter::Owner(uid) => match &file.unix {
+                Some(unix) => unix.uid == *uid,
+                None => fs::symlink_metadata(&file.path)
+                    .map(|metadata| metadata.uid() == *uid)
+                    .unwrap_or(false),
+            },
+            Filter::Extension(extension) => file
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl Comparison {
+    fn test<T: PartialOrd>(&self, value: T, reference: T) -> bool {
+        match self {
+            Comparison::Less => value < reference,
+            Comparison::LessOrEqual => value <= reference,
+            Comparison::Equal => value == reference,
+            Comparison::GreaterOrEqual => value >= reference,
+            Comparison::Greater => value > reference,
+        }
+    }
+
+    /// The comparison with its sides swapped, e.g. `<` becomes `>`.
+    fn reversed(self) -> Self {
+        match self {
+            Comparison::Less => Comparison::Greater,
+            Comparison::LessOrEqual => Comparison::GreaterOrEqual,
+            Comparison::Equal => Comparison::Equal,
+            Comparison::GreaterOrEqual => Comparison::LessOrEqual,
+            Comparison::Greater => Comparison::Less,
+        }
+    }
+}
+
+impl FileKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "file" => Some(FileKind::File),
+            "folder" | "dir" | "directory" => Some(FileKind::Folder),
+            "image" => Some(FileKind::Image),
+            "audio" | "music" => Some(FileKind::Audio),
+            "video" => Some(FileKind::Video),
+            "document" | "doc" => Some(FileKind::Document),
+            "archive" => Some(FileKind::Archive),
+            "code" => Some(FileKind::Code),
+            "text" => Some(FileKind::Text),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, file: &FileInfo) -> bool {
+        if file.is_dir {
+            return *self == FileKind::Folder;
+        }
+
+        let mime_type = file.mime_type.as_str();
+        match self {
+            FileKind::File => true,
+            FileKind::Folder => false,
+            FileKind::Image => mime_type.starts_with("image/"),
+            FileKind::Audio => mime_type.starts_with("audio/"),
+            FileKind::Video => mime_type.starts_with("video/"),
+            FileKind::Document => {
+                mime_type == "application/pdf"
+                    || mime_type == "application/msword"
+                    || mime_type.starts_with("application/vnd.oasis.opendocument")
+                    || mime_type.starts_with("application/vnd.openxmlformats-officedocument")
+            }
+            FileKind::Archive => matches!(
+                mime_type,
+                "application/zip"
+                    | "application/x-tar"
+                    | "application/gzip"
+                    | "application/x-bzip2"
+                    | "application/x-xz"
+                    | "application/x-7z-compressed"
+                    | "application/vnd.rar"
+                    | "application/x-compressed-tar"
+            ),
+            // Source files are text subtypes such as text/x-rust
+            FileKind::Code => {
+                !is_plain_text(mime_type)
+                    && (gio::content_type_is_a(mime_type, "text/plain")
+                        || matches!(
+                            mime_type,
+                            "application/x-shellscript"
+                                | "application/javascript"
+                                | "application/json"
+                        ))
+            }
+            FileKind::Text => is_plain_text(mime_type),
+        }
+    }
+}
+
+/// Prose rather than code: plain text, Markdown and the like.
+fn is_plain_text(mime_type: &str) -> bool {
+    matches!(
+        mime_type,
+        "text/plain" | "text/markdown" | "text/x-readme" | "text/x-log" | "text/x-rst"
+    )
+}
+
+/// Splits on whitespace, keeping double-quoted sections together. Each token
+/// records whether any part of it was quoted.
+fn tokenize(input: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() || quoted {
+                    tokens.push((std::mem::take(&mut current), quoted));
+                }
+                quoted = false;
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() || quoted {
+        tokens.push((current, quoted));
+    }
+
+    tokens
+}
+
+/// Anything that looks like `word:` is treated as a filter, so that typos in
+/// filter names are reported instead of silently searched for.
+fn is_filter_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn parse_filter(token: &str, key: &str, value: &str) -> Result<Filter, QueryError> {
+    let error = |message: String| QueryError {
+        token: token.to_string(),
+        message,
+    };
+
+    if value.is_empty() {
+        return Err(error(format!("missing value after '{}:'", key)));
+    }
+
+    match key.to_lowercase().as_str() {
+        "type" | "kind" => FileKind::from_name(value).map(Filter::Kind).ok_or_else(|| {
+            error(
+                "unknown type; use file, folder, image, audio, video, document, archive, code or text"
+                    .to_string(),
+            )
+        }),
+        "size" => {
+            let (comparison, amount) = split_comparison(value);
+            parse_size(amount)
+                .map(|size| Filter::Size(comparison.unwrap_or(Comparison::Equal), size))
+                .ok_or_else(|| error("invalid size; use e.g. size:>10M or size:<=512k".to_string()))
+        }
+        "modified" | "mtime" => {
+            let (comparison, amount) = split_comparison(value);
+            parse_time(comparison, amount).ok_or_else(|| {
+                error(
+                    "invalid time; use an age such as modified:<7d or a date such as modified:>2024-01-31"
+                        .to_string(),
+                )
+            })
+        }
+        "owner" | "user" => users::uid_for_name(value)
+            .map(Filter::Owner)
+            .ok_or_else(|| error(format!("no user named '{}'", value))),
+        "ext" | "extension" => Ok(Filter::Extension(
+            value.trim_start_matches('.').to_lowercase(),
+        )),
+        _ => Err(error(format!(
+            "unknown filter '{}'; known filters are {} (quote the word to search for it as text)",
+            key, FILTER_NAMES
+        ))),
+    }
+}
+
+fn split_comparison(value: &str) -> (Option<Comparison>, &str) {
+    for (prefix, comparison) in [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (Some(comparison), rest);
+        }
+    }
+    (None, value)
+}
+
+/// Parses sizes such as `512`, `10k`, `1.5M`, `2GB` or `4GiB` (powers of 1024).
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let unit = unit.to_lowercase();
+    let unit = unit
+        .strip_suffix("ib")
+        .or_else(|| unit.strip_suffix('b'))
+        .unwrap_or(&unit);
+    let multiplier: u64 = match unit {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return None,
+    };
+
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Turns an age (`7d`, `2w`, `3h`) or a date (`2024-01-31`) into a bound on
+/// the modification time. Ages count backwards from now, so `<7d` ("less than
+/// a week old") means "modified after a week ago". A bare age means "within".
+fn parse_time(comparison: Option<Comparison>, value: &str) -> Option<Filter> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        // Exact timestamps are never what's meant, so dates need a direction
+        let comparison = comparison.filter(|c| *c != Comparison::Equal)?;
+        let midnight = date.and_hms_opt(0, 0, 0)?;
+        let time = Local.from_local_datetime(&midnight).earliest()?;
+        return Some(Filter::Modified(comparison, time.into()));
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "mo" => 30 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    let comparison = comparison.unwrap_or(Comparison::Less);
+    let age = Duration::from_secs(number.checked_mul(seconds)?);
+    let time = SystemTime::now().checked_sub(age)?;
+    Some(Filter::Modified(comparison.reversed(), time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_filters(input: &str) -> Vec<Filter> {
+        Query::parse(input).unwrap().filters
+    }
+
+    fn parse_error(input: &str) -> String {
+        Query::parse(input).unwrap_err().to_string()
+    }
+
+    /// Unpacks a `modified:` filter, checking its time lies `age` before now.
+    fn assert_age(filter: &Filter, comparison: Comparison, age: Duration) {
+        let Filter::Modified(actual, time) = filter else {
+            panic!("expected a modified filter, got {:?}", filter);
+        };
+        assert_eq!(*actual, comparison);
+        let actual_age = SystemTime::now().duration_since(*time).unwrap();
+        assert!(actual_age >= age && actual_age < age + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn splits_text_and_filters() {
+        let query = Query::parse("quarterly report type:document size:>10M").unwrap();
+        assert_eq!(query.text, "quarterly report");
+        assert_eq!(
+            query.filters,
+            vec![
+                Filter::Kind(FileKind::Document),
+                Filter::Size(Comparison::Greater, 10 << 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_types() {
+        assert_eq!(
+            parse_filters("type:image"),
+            vec![Filter::Kind(FileKind::Image)]
+        );
+        assert_eq!(
+            parse_filters("kind:dir"),
+            vec![Filter::Kind(FileKind::Folder)]
+        );
+        assert_eq!(
+            parse_filters("type:Music"),
+            vec![Filter::Kind(FileKind::Audio)]
+        );
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(
+            parse_filters("size:512"),
+            vec![Filter::Size(Comparison::Equal, 512)]
+        );
+        assert_eq!(
+            parse_filters("size:<=1.5k"),
+            vec![Filter::Size(Comparison::LessOrEqual, 1536)]
+        );
+        assert_eq!(
+            parse_filters("size:>=2GiB"),
+            vec![Filter::Size(Comparison::GreaterOrEqual, 2 << 30)]
+        );
+        assert_eq!(
+            parse_filters("size:<1tb"),
+            vec![Filter::Size(Comparison::Less, 1 << 40)]
+        );
+    }
+
+    #[test]
+    fn parses_ages() {
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        // "Less than a week old" means modified after a week ago
+        assert_age(
+            &parse_filters("modified:<7d")[0],
+            Comparison::Greater,
+            7 * day,
+        );
+        assert_age(&parse_filters("mtime:>2w")[0], Comparison::Less, 14 * day);
+        // A bare age means "within"
+        assert_age(
+            &parse_filters("modified:3")[0],
+            Comparison::Greater,
+            3 * day,
+        );
+    }
+
+    #[test]
+    fn parses_dates() {
+        let midnight = NaiveDate::from_ymd_opt(2024, 1, 31)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let time: SystemTime = Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .unwrap()
+            .into();
+        assert_eq!(
+            parse_filters("modified:>2024-01-31"),
+            vec![Filter::Modified(Comparison::Greater, time)]
+        );
+    }
+
+    #[test]
+    fn parses_owners() {
+        assert_eq!(parse_filters("owner:1234"), vec![Filter::Owner(1234)]);
+        assert_eq!(parse_filters("user:root"), vec![Filter::Owner(0)]);
+    }
+
+    #[test]
+    fn parses_extensions() {
+        assert_eq!(
+            parse_filters("ext:.RS"),
+            vec![Filter::Extension("rs".to_string())]
+        );
+        assert_eq!(
+            parse_filters("extension:tar"),
+            vec![Filter::Extension("tar".to_string())]
+        );
+    }
+
+    #[test]
+    fn quoting_keeps_words_as_text() {
+        let query = Query::parse(r#""12:30" meeting "type:image""#).unwrap();
+        assert_eq!(query.text, "12:30 meeting type:image");
+        assert!(query.filters.is_empty());
+
+        let query = Query::parse(r#""two words" ext:md"#).unwrap();
+        assert_eq!(query.text, "two words");
+        assert_eq!(query.filters, vec![Filter::Extension("md".to_string())]);
+    }
+
+    #[test]
+    fn reports_unknown_filters() {
+        assert_eq!(
+            parse_error("colour:red"),
+            "colour:red: unknown filter 'colour'; known filters are type, size, modified, \
+             owner, ext (quote the word to search for it as text)"
+        );
+    }
+
+    #[test]
+    fn reports_missing_values() {
+        assert_eq!(parse_error("type:"), "type:: missing value after 'type:'");
+    }
+
+    #[test]
+    fn reports_bad_values() {
+        assert_eq!(
+            parse_error("type:spreadsheet"),
+            "type:spreadsheet: unknown type; use file, folder, image, audio, video, document, \
+             archive, code or text"
+        );
+        assert_eq!(
+            parse_error("size:big"),
+            "size:big: invalid size; use e.g. size:>10M or size:<=512k"
+        );
+        assert_eq!(
+            parse_error("size:10q"),
+            "size:10q: invalid size; use e.g. size:>10M or size:<=512k"
+        );
+        assert_eq!(
+            parse_error("owner:no-such-user-zfile"),
+            "owner:no-such-user-zfile: no user named 'no-such-user-zfile'"
+        );
+    }
+
+    #[test]
+    fn reports_bad_times() {
+        let message = "invalid time; use an age such as modified:<7d or a date such as \
+                       modified:>2024-01-31";
+        assert_eq!(
+            parse_error("modified:soon"),
+            format!("modified:soon: {}", message)
+        );
+        // Dates need a direction
+        assert_eq!(
+            parse_error("modified:2024-01-31"),
+            format!("modified:2024-01-31: {}", message)
+        );
+        // Ages too large to count back from now
+        assert_eq!(
+            parse_error("modified:<99999999999999999y"),
+            format!("modified:<99999999999999999y: {}", message)
+        );
+    }
+}
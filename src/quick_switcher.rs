@@ -0,0 +1,173 @@
+use crate::fuzzy;
+use crate::palette::{Palette, PaletteItem};
+use crate::services::AppServices;
+use gtk::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Indexed folders considered per query, on top of the known locations.
+const MAX_INDEXED_CANDIDATES: usize = 100;
+/// Bookmarks rank above other locations of the same frecency.
+const BOOKMARK_BONUS: i64 = 500;
+
+/// Where a destination in the quick switcher comes from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Source {
+    Bookmark,
+    Recent,
+    History,
+    Indexed,
+}
+
+impl Source {
+    fn icon_name(&self) -> &'static str {
+        match self {
+            Source::Bookmark => "user-bookmarks-symbolic",
+            Source::Recent => "document-open-recent-symbolic",
+            Source::History => "go-previous-symbolic",
+            Source::Indexed => "folder-symbolic",
+        }
+    }
+}
+
+/// Ctrl+P "go to folder" palette, fuzzy-matching bookmarks, visited folders,
+/// the window's history and indexed folders, ranked by frecency.
+#[derive(Clone)]
+pub struct QuickSwitcher {
+    palette: Palette,
+    destinations: Rc<RefCell<Vec<PathBuf>>>,
+    /// Visited folders that still exist, listed once per popup since
+    /// checking them takes a `stat` each.
+    visited: Rc<RefCell<Option<Vec<PathBuf>>>>,
+}
+
+impl QuickSwitcher {
+    /// `history` lists the folders in the window's back/forward history.
+    pub fn new<H>(parent: &impl IsA<gtk::Widget>, services: &AppServices, history: H) -> Self
+    where
+        H: Fn() -> Vec<PathBuf> + 'static,
+    {
+        let palette = Palette::new(parent, "Go to folder…");
+        let destinations: Rc<RefCell<Vec<PathBuf>>> = Rc::new(RefCell::new(Vec::new()));
+        let visited: Rc<RefCell<Option<Vec<PathBuf>>>> = Rc::new(RefCell::new(None));
+
+        let services = services.clone();
+        let destinations_clone = destinations.clone();
+        let visited_clone = visited.clone();
+        palette.connect_items(move |pattern| {
+            let visited = visited_clone
+                .borrow_mut()
+                .get_or_insert_with(|| services.locations.list())
+                .clone();
+            let ranked = rank(
+                pattern,
+                candidates(&services, &visited, &history(), pattern),
+                &services,
+            );
+            let items = ranked
+                .iter()
+                .map(|(path, source)| PaletteItem {
+                    title: display_name(path),
+                    subtitle: path.to_string_lossy().to_string(),
+                    icon_name: source.icon_name(),
+                    shortcut: None,
+                })
+                .collect();
+            *destinations_clone.borrow_mut() = ranked.into_iter().map(|(path, _)| path).collect();
+            items
+        });
+
+        QuickSwitcher {
+            palette,
+            destinations,
+            visited,
+        }
+    }
+
+    pub fn popup(&self) {
+        *self.visited.borrow_mut() = None;
+        self.palette.popup();
+    }
+
+    pub fn connect_navigate<F>(&self, callback: F)
+    where
+        F: Fn(PathBuf) + 'static,
+    {
+        let destinations = self.destinations.clone();
+        self.palette.connect_activated(move |index| {
+            let path = destinations.borrow().get(index).cloned();
+            if let Some(path) = path {
+                callback(path);
+            }
+        });
+    }
+}
+
+/// Gathers the folders to choose from, each listed once under its most
+/// personal source.
+fn candidates(
+    services: &AppServices,
+    visited: &[PathBuf],
+    history: &[PathBuf],
+    pattern: &str,
+) -> Vec<(PathBuf, Source)> {
+    let bookmarks = services
+        .bookmarks
+        .list()
+        .into_iter()
+        .filter_map(|bookmark| bookmark.path())
+        .map(|p| (p, Source::Bookmark));
+    let recent = visited.iter().cloned().map(|p| (p, Source::Recent));
+    let history = history.iter().rev().cloned().map(|p| (p, Source::History));
+    let indexed = services
+        .file_index
+        .matching_directories(pattern, MAX_INDEXED_CANDIDATES)
+        .into_iter()
+        .map(|p| (p, Source::Indexed));
+
+    let mut seen = HashSet::new();
+    bookmarks
+        .chain(recent)
+        .chain(history)
+        .chain(indexed)
+        .filter(|(path, _)| seen.insert(path.clone()))
+        .collect()
+}
+
+/// Orders the candidates matching `pattern` by how well the folder name (or
+/// failing that, the whole path) matches, plus frecency.
+fn rank(
+    pattern: &str,
+    candidates: Vec<(PathBuf, Source)>,
+    services: &AppServices,
+) -> Vec<(PathBuf, Source)> {
+    let mut ranked: Vec<(i64, PathBuf, Source)> = candidates
+        .into_iter()
+        .filter_map(|(path, source)| {
+            let match_score = fuzzy::score(pattern, &display_name(&path)).or_else(|| {
+                fuzzy::score(pattern, &path.to_string_lossy()).map(|score| score / 2)
+            })?;
+            let bonus = if source == Source::Bookmark {
+                BOOKMARK_BONUS
+            } else {
+                0
+            };
+            let score = match_score + services.locations.score(&path) as i64 + bonus;
+            Some((score, path, source))
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.0.cmp(&a.0));
+    ranked
+        .into_iter()
+        .map(|(_, path, source)| (path, source))
+        .collect()
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
@@ -0,0 +1,70 @@
+use crate::file_info::FileInfo;
+use chrono::{DateTime, Local};
+use gtk::prelude::*;
+use std::path::Path;
+
+/// Most files listed in the Recent view.
+const MAX_RECENT_FILES: usize = 200;
+
+/// A file from the recently used list shared by desktop applications,
+/// `~/.local/share/recently-used.xbel`.
+pub struct RecentFile {
+    pub file: FileInfo,
+    pub visited: DateTime<Local>,
+    /// The application that used the file last, e.g. "gedit".
+    pub app_name: Option<String>,
+}
+
+impl RecentFile {
+    /// "gedit · 2024-05-01 14:02", shown under the file name.
+    pub fn description(&self) -> String {
+        let visited = self.visited.format("%Y-%m-%d %H:%M").to_string();
+        match &self.app_name {
+            Some(app_name) => format!("{} · {}", app_name, visited),
+            None => visited,
+        }
+    }
+}
+
+/// Local files that still exist, most recently used first. GTK's
+/// `RecentManager` parses the list and reloads it when it changes on disk.
+pub fn list() -> Vec<RecentFile> {
+    let mut items: Vec<gtk::RecentInfo> = gtk::RecentManager::default()
+        .items()
+        .into_iter()
+        .filter(|info| info.is_local())
+        .collect();
+    // Only the files shown are checked, which takes a `stat` each
+    items.sort_by_key(|info| std::cmp::Reverse(info.visited().to_unix()));
+    items.truncate(MAX_RECENT_FILES);
+
+    items
+        .into_iter()
+        .filter_map(|info| {
+            let path = gio::File::for_uri(&info.uri()).path()?;
+            // Fails for files that were deleted or moved since
+            let mut file = FileInfo::from_path(&path).ok()?;
+            // The type recorded with the item saves reading the file
+            let recorded_type = info.mime_type();
+            if file.needs_sniffing && !recorded_type.is_empty() {
+                file.mime_type = recorded_type.to_string();
+                file.needs_sniffing = false;
+            }
+            let visited = DateTime::from_timestamp(info.visited().to_unix(), 0)?;
+            let app_name = Some(info.last_application().to_string()).filter(|n| !n.is_empty());
+            Some(RecentFile {
+                file,
+                visited: visited.with_timezone(&Local),
+                app_name,
+            })
+        })
+        .collect()
+}
+
+/// Adds `path` to the recently used list so other applications see it.
+pub fn record(path: &Path) {
+    let uri = gio::File::for_path(path).uri();
+    if !gtk::RecentManager::default().add_item(&uri) {
+        eprintln!("Failed to add {} to recent files", path.display());
+    }
+}
@@ -0,0 +1,645 @@
+use crate::file_info::FileInfo;
+use crate::query::Query;
+use crate::search::{glob_class, push_literal, NameMatcher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+const BATCH_SIZE: usize = 64;
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Files larger than this are not searched by content.
+const MAX_CONTENT_SEARCH_SIZE: u64 = 10 * 1024 * 1024;
+const BINARY_CHECK_LENGTH: usize = 8192;
+const MAX_SNIPPET_LENGTH: usize = 120;
+
+/// Which parts of the tree a recursive walk descends into.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalkOptions {
+    pub skip_hidden: bool,
+    pub follow_symlinks: bool,
+    pub same_filesystem: bool,
+    pub respect_gitignore: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            skip_hidden: true,
+            follow_symlinks: false,
+            same_filesystem: true,
+            respect_gitignore: true,
+        }
+    }
+}
+
+/// A running background search. Dropping or cancelling it stops the worker
+/// and guarantees no further results are delivered.
+pub struct SearchHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SearchHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Searches `root` recursively for names accepted by `matcher` that also pass
+/// the filters of `query`, on a worker thread. Results are delivered in
+/// batches to `on_results` on the main thread, followed by `on_finished`
+/// unless the search was cancelled.
+pub fn spawn_search<R, D>(
+    root: PathBuf,
+    matcher: NameMatcher,
+    query: Query,
+    options: WalkOptions,
+    on_results: R,
+    on_finished: D,
+) -> SearchHandle
+where
+    R: Fn(Vec<FileInfo>) + 'static,
+    D: FnOnce() + 'static,
+{
+    spawn_walker(
+        root,
+        options,
+        move |path, name| {
+            if !matcher.is_match(name) {
+                return None;
+            }
+            // Sniffed here on the worker, so the view doesn't have to
+            let mut file_info = FileInfo::from_path(path).ok()?;
+            file_info.sniff();
+            query.matches(&file_info).then_some(file_info)
+        },
+        on_results,
+        on_finished,
+    )
+}
+
+/// A file whose contents matched a content search.
+#[derive(Clone, Debug)]
+pub struct ContentMatch {
+    pub file: FileInfo,
+    /// 1-based number of the first matching line.
+    pub line_number: usize,
+    pub line: String,
+}
+
+impl ContentMatch {
+    pub fn snippet(&self) -> String {
+        format!("{}: {}", self.line_number, self.line)
+    }
+}
+
+/// Searches the contents of the text files below `root` that pass the filters
+/// of `query` for `pattern` on a worker thread, like `spawn_search`. Binary
+/// files and files larger than `MAX_CONTENT_SEARCH_SIZE` are skipped.
+pub fn spawn_content_search<R, D>(
+    root: PathBuf,
+    pattern: Regex,
+    query: Query,
+    options: WalkOptions,
+    on_results: R,
+    on_finished: D,
+) -> SearchHandle
+where
+    R: Fn(Vec<ContentMatch>) + 'static,
+    D: FnOnce() + 'static,
+{
+    spawn_walker(
+        root,
+        options,
+        move |path, _| find_in_file(path, &pattern, &query),
+        on_results,
+        on_finished,
+    )
+}
+
+/// Walks `root` on a worker thread, streaming whatever `visit` returns back
+/// to the main thread in batches.
+fn spawn_walker<T, V, R, D>(
+    root: PathBuf,
+    options: WalkOptions,
+    mut visit: V,
+    on_results: R,
+    on_finished: D,
+) -> SearchHandle
+where
+    T: Send + 'static,
+    V: FnMut(&Path, &str) -> Option<T> + Send + 'static,
+    R: Fn(Vec<T>) + 'static,
+    D: FnOnce() + 'static,
+{
+    spawn_worker(
+        move |mut results| {
+            let cancelled = results.cancelled.clone();
+            walk(&root, options, &cancelled, |path, name| {
+                if let Some(result) = visit(path, name) {
+                    results.push(result);
+                }
+                results.flush_if_due();
+                true
+            });
+        },
+        on_results,
+        on_finished,
+    )
+}
+
+/// Runs `work` on a worker thread, delivering the results it sends in
+/// batches to `on_results` on the main thread, followed by `on_finished`
+/// unless the search was cancelled.
+pub fn spawn_worker<T, W, R, D>(work: W, on_results: R, on_finished: D) -> SearchHandle
+where
+    T: Send + 'static,
+    W: FnOnce(ResultSender<T>) + Send + 'static,
+    R: Fn(Vec<T>) + 'static,
+    D: FnOnce() + 'static,
+{
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<T>>();
+
+    let results = ResultSender {
+        sender,
+        batch: Vec::new(),
+        last_flush: Instant::now(),
+        cancelled: cancelled.clone(),
+    };
+    thread::spawn(move || work(results));
+
+    let receiver_cancelled = cancelled.clone();
+    glib::spawn_future_local(async move {
+        while let Some(batch) = receiver.recv().await {
+            if receiver_cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            on_results(batch);
+        }
+
+        if !receiver_cancelled.load(Ordering::Relaxed) {
+            on_finished();
+        }
+    });
+
+    SearchHandle { cancelled }
+}
+
+/// The worker's end of a search started with `spawn_worker`. Results are
+/// sent on in batches; the last one when it is dropped.
+pub struct ResultSender<T> {
+    sender: mpsc::UnboundedSender<Vec<T>>,
+    batch: Vec<T>,
+    last_flush: Instant,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<T> ResultSender<T> {
+    pub fn push(&mut self, result: T) {
+        self.batch.push(result);
+        self.flush_if_due();
+    }
+
+    /// Sends the pending results once the batch is full or has waited long
+    /// enough.
+    pub fn flush_if_due(&mut self) {
+        let due = self.batch.len() >= BATCH_SIZE || self.last_flush.elapsed() >= FLUSH_INTERVAL;
+        if due && !self.batch.is_empty() {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.sender.send(std::mem::take(&mut self.batch)).is_err() {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+        self.last_flush = Instant::now();
+    }
+
+    /// Whether the search was cancelled, so the worker should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for ResultSender<T> {
+    fn drop(&mut self) {
+        if !self.batch.is_empty() {
+            self.flush();
+        }
+    }
+}
+
+/// Returns the first line of `path` matching `pattern`, if it is a text file
+/// small enough to search that passes the filters of `query`.
+fn find_in_file(path: &Path, pattern: &Regex, query: &Query) -> Option<ContentMatch> {
+    // Only regular files: reading a FIFO or a device can block for good
+    if !fs::metadata(path).ok()?.is_file() {
+        return None;
+    }
+
+    let mut file = FileInfo::from_path(path).ok()?;
+    file.sniff();
+    if file.size > MAX_CONTENT_SEARCH_SIZE || !query.matches(&file) {
+        return None;
+    }
+
+    let contents = fs::read(path).ok()?;
+    // Treat files with NUL bytes near the start as binary, like grep does
+    let head = &contents[..contents.len().min(BINARY_CHECK_LENGTH)];
+    if head.contains(&0) {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&contents);
+    let (index, line) = text
+        .lines()
+        .enumerate()
+        .find(|(_, line)| pattern.is_match(line))?;
+
+    Some(ContentMatch {
+        file,
+        line_number: index + 1,
+        line: snippet_text(line),
+    })
+}
+
+/// Trims a matching line down to something that fits in a label.
+fn snippet_text(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() <= MAX_SNIPPET_LENGTH {
+        return line.to_string();
+    }
+    let mut snippet: String = line.chars().take(MAX_SNIPPET_LENGTH).collect();
+    snippet.push('…');
+    snippet
+}
+
+/// Walks the tree below `root` depth-first, calling `visit` with the path and
+/// file name of every entry that `options` does not exclude. Returning `false`
+/// from `visit` skips the entry's contents.
+pub fn walk<F>(root: &Path, options: WalkOptions, cancelled: &AtomicBool, mut visit: F)
+where
+    F: FnMut(&Path, &str) -> bool,
+{
+    let Ok(root_metadata) = fs::metadata(root) else {
+        return;
+    };
+
+    let mut visited = HashSet::new();
+    visited.insert((root_metadata.dev(), root_metadata.ino()));
+
+    let mut stack: Vec<(PathBuf, Rc<Vec<IgnoreRule>>)> =
+        vec![(root.to_path_buf(), Rc::new(Vec::new()))];
+
+    while let Some((dir, inherited_rules)) = stack.pop() {
+        let rules = if options.respect_gitignore {
+            let local_rules = parse_gitignore(&dir);
+            if local_rules.is_empty() {
+                inherited_rules
+            } else {
+                let mut rules = (*inherited_rules).clone();
+                rules.extend(local_rules);
+                Rc::new(rules)
+            }
+        } else {
+            inherited_rules
+        };
+
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            if options.skip_hidden && name.starts_with('.') {
+                continue;
+            }
+
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let is_dir = if file_type.is_symlink() {
+                options.follow_symlinks && path.is_dir()
+            } else {
+                file_type.is_dir()
+            };
+
+            if options.respect_gitignore && (name == ".git" || is_ignored(&rules, &path, is_dir)) {
+                continue;
+            }
+
+            if !visit(&path, &name) || !is_dir {
+                continue;
+            }
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            if options.same_filesystem && metadata.dev() != root_metadata.dev() {
+                continue;
+            }
+            // Guards against symlink loops
+            if visited.insert((metadata.dev(), metadata.ino())) {
+                stack.push((path, rules.clone()));
+            }
+        }
+    }
+}
+
+/// Tells which paths below a root a walk with `respect_gitignore` would
+/// skip, for results that don't come from a walk. Each folder's rules are
+/// read once.
+pub struct GitignoreFilter {
+    root: PathBuf,
+    rules: HashMap<PathBuf, Rc<Vec<IgnoreRule>>>,
+}
+
+impl GitignoreFilter {
+    pub fn new(root: &Path) -> Self {
+        GitignoreFilter {
+            root: root.to_path_buf(),
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Whether `path`, or a folder between the root and it, is ignored.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        let components: Vec<_> = relative.components().collect();
+        let mut dir = self.root.clone();
+        for (index, component) in components.iter().enumerate() {
+            let rules = self.rules_for(&dir);
+            let child = dir.join(component);
+            let child_is_dir = index + 1 < components.len() || is_dir;
+            if component.as_os_str() == ".git" || is_ignored(&rules, &child, child_is_dir) {
+                return true;
+            }
+            dir = child;
+        }
+        false
+    }
+
+    /// The rules that apply to entries of `dir`: its own and its parents'.
+    fn rules_for(&mut self, dir: &Path) -> Rc<Vec<IgnoreRule>> {
+        if let Some(rules) = self.rules.get(dir) {
+            return rules.clone();
+        }
+
+        let mut rules = match dir.parent() {
+            Some(parent) if dir != self.root => (*self.rules_for(parent)).clone(),
+            _ => Vec::new(),
+        };
+        rules.extend(parse_gitignore(dir));
+        let rules = Rc::new(rules);
+        self.rules.insert(dir.to_path_buf(), rules.clone());
+        rules
+    }
+}
+
+/// One pattern from a `.gitignore` file.
+#[derive(Clone)]
+struct IgnoreRule {
+    base: PathBuf,
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+fn parse_gitignore(dir: &Path) -> Vec<IgnoreRule> {
+    let Ok(contents) = fs::read_to_string(dir.join(".gitignore")) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| parse_ignore_line(dir, line))
+        .collect()
+}
+
+/// Parses a line of the `.gitignore` in `base`; `None` for blank lines and
+/// comments. Patterns with a slash before the end are matched against the
+/// path below `base`, others against names at any depth.
+fn parse_ignore_line(base: &Path, line: &str) -> Option<IgnoreRule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let anchored = pattern.contains('/');
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+
+    Regex::new(&gitignore_to_regex(pattern))
+        .ok()
+        .map(|regex| IgnoreRule {
+            base: base.to_path_buf(),
+            regex,
+            negated,
+            dir_only,
+            anchored,
+        })
+}
+
+/// Translates a `.gitignore` pattern into an anchored regular expression.
+/// `*`, `?` and classes stay within one path component; `**/` matches any
+/// number of folders and a trailing `/**` everything inside. Other runs of
+/// asterisks are plain ones, and a backslash makes the next character literal.
+fn gitignore_to_regex(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut regex = String::from("^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let whole_component = i == 0 || chars[i - 1] == '/';
+                i += 2;
+                if whole_component && chars.get(i) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 1;
+                } else if whole_component && i == chars.len() {
+                    regex.push_str(".*");
+                } else {
+                    regex.push_str("[^/]*");
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                if let Some((class, end)) = glob_class(&chars, i) {
+                    regex.push_str(&class);
+                    i = end;
+                    continue;
+                }
+                push_literal(&mut regex, '[');
+            }
+            '\\' => {
+                if let Some(&next) = chars.get(i + 1) {
+                    push_literal(&mut regex, next);
+                    i += 1;
+                }
+            }
+            c => push_literal(&mut regex, c),
+        }
+        i += 1;
+    }
+    regex.push('$');
+    regex
+}
+
+/// Applies the rules in order; the last matching rule decides.
+fn is_ignored(rules: &[IgnoreRule], path: &Path, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for rule in rules {
+        if rule.dir_only && !is_dir {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(&rule.base) else {
+            continue;
+        };
+
+        let subject = if rule.anchored {
+            relative.to_string_lossy()
+        } else {
+            path.file_name().unwrap_or_default().to_string_lossy()
+        };
+        if rule.regex.is_match(&subject) {
+            ignored = !rule.negated;
+        }
+    }
+    ignored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `path` below `/repo` is ignored by a `.gitignore` there
+    /// made of `lines`.
+    fn ignored(lines: &[&str], path: &str, is_dir: bool) -> bool {
+        let base = Path::new("/repo");
+        let rules: Vec<IgnoreRule> = lines
+            .iter()
+            .filter_map(|line| parse_ignore_line(base, line))
+            .collect();
+        is_ignored(&rules, &base.join(path), is_dir)
+    }
+
+    fn check(cases: &[(&[&str], &str, bool, bool)]) {
+        for (lines, path, is_dir, expected) in cases {
+            assert_eq!(
+                ignored(lines, path, *is_dir),
+                *expected,
+                "{:?} on {}",
+                lines,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn matches_names_at_any_depth() {
+        check(&[
+            (&["*.o"], "x.o", false, true),
+            (&["*.o"], "src/deep/x.o", false, true),
+            (&["*.o"], "x.oo", false, false),
+            (&["target"], "sub/target", true, true),
+            (&["?.txt"], "a.txt", false, true),
+            (&["?.txt"], "ab.txt", false, false),
+            (&["[ab].c"], "b.c", false, true),
+            (&["[!ab].c"], "b.c", false, false),
+        ]);
+    }
+
+    #[test]
+    fn anchors_patterns_with_a_slash() {
+        check(&[
+            (&["/foo"], "foo", false, true),
+            (&["/foo"], "sub/foo", false, false),
+            (&["doc/*.txt"], "doc/a.txt", false, true),
+            (&["doc/*.txt"], "sub/doc/a.txt", false, false),
+            (&["/build/*.o"], "build/x.o", false, true),
+            (&["/build/*.o"], "build/sub/x.o", false, false),
+        ]);
+    }
+
+    #[test]
+    fn matches_double_asterisks_anywhere() {
+        check(&[
+            (&["**/foo"], "foo", false, true),
+            (&["**/foo"], "a/b/foo", false, true),
+            (&["**/foo/bar"], "a/foo/bar", false, true),
+            (&["a/**/b"], "a/b", false, true),
+            (&["a/**/b"], "a/x/y/b", false, true),
+            (&["a/**/b"], "a/x/c", false, false),
+            (&["a/**"], "a/x/y", false, true),
+            (&["a/**"], "a", true, false),
+            (&["a**b"], "axyb", false, true),
+            (&["/a**b"], "ax/yb", false, false),
+        ]);
+    }
+
+    #[test]
+    fn matches_directory_only_patterns_on_folders() {
+        check(&[
+            (&["build/"], "build", true, true),
+            (&["build/"], "build", false, false),
+            (&["build/"], "src/build", true, true),
+            (&["/out/"], "src/out", true, false),
+        ]);
+    }
+
+    #[test]
+    fn lets_the_last_matching_rule_decide() {
+        check(&[
+            (&["*.log", "!keep.log"], "a.log", false, true),
+            (&["*.log", "!keep.log"], "keep.log", false, false),
+            (&["!keep.log", "*.log"], "keep.log", false, true),
+        ]);
+    }
+
+    #[test]
+    fn skips_comments_and_honours_escapes() {
+        check(&[
+            (&["# comment"], "# comment", false, false),
+            (&["\\#file"], "#file", false, true),
+            (&["\\!bang"], "!bang", false, true),
+            (&["a\\*"], "a*", false, true),
+            (&["a\\*"], "ab", false, false),
+            (&[""], "anything", false, false),
+        ]);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::search::SearchState;
+use crate::services::ChangeHandlers;
+use crate::settings::config_dir;
+use gtk::prelude::*;
+use gtk::Window;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// A named search that the sidebar shows like a folder.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub root: PathBuf,
+    #[serde(flatten)]
+    pub search: SearchState,
+}
+
+fn saved_searches_path() -> PathBuf {
+    config_dir().join("saved-searches.json")
+}
+
+/// Saved searches, stored in `$XDG_CONFIG_HOME/zfile/saved-searches.json` and
+/// shared so that every window's sidebar stays in sync.
+#[derive(Clone)]
+pub struct SavedSearchStore {
+    searches: Rc<RefCell<Vec<SavedSearch>>>,
+    handlers: ChangeHandlers,
+}
+
+impl SavedSearchStore {
+    pub fn load() -> Self {
+        let searches = match fs::read_to_string(saved_searches_path()) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Failed to parse saved searches: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        SavedSearchStore {
+            searches: Rc::new(RefCell::new(searches)),
+            handlers: ChangeHandlers::new(),
+        }
+    }
+
+    pub fn list(&self) -> Vec<SavedSearch> {
+        self.searches.borrow().clone()
+    }
+
+    /// Adds `search`, replacing any saved search with the same name.
+    pub fn add(&self, search: SavedSearch) {
+        {
+            let mut searches = self.searches.borrow_mut();
+            match searches.iter_mut().find(|s| s.name == search.name) {
+                Some(existing) => *existing = search,
+                None => searches.push(search),
+            }
+        }
+        self.changed();
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.searches.borrow_mut().retain(|s| s.name != name);
+        self.changed();
+    }
+
+    pub fn connect_changed<F>(&self, callback: F) -> usize
+    where
+        F: Fn() + 'static,
+    {
+        self.handlers.connect(move |_| callback())
+    }
+
+    pub fn disconnect(&self, handler_id: usize) {
+        self.handlers.disconnect(handler_id);
+    }
+
+    fn changed(&self) {
+        if let Err(e) = save(&self.searches.borrow()) {
+            eprintln!("Failed to save saved searches: {}", e);
+        }
+
+        self.handlers.notify(&());
+    }
+}
+
+fn save(searches: &[SavedSearch]) -> std::io::Result<()> {
+    fs::create_dir_all(config_dir())?;
+    let contents = serde_json::to_string_pretty(searches)?;
+    fs::write(saved_searches_path(), contents)
+}
+
+/// Asks for the name to save a search under and passes it to `on_save`.
+pub fn show_save_search_dialog<F>(parent: &impl IsA<Window>, suggested_name: &str, on_save: F)
+where
+    F: Fn(String) + 'static,
+{
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Save Search"),
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::USE_HEADER_BAR,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Save", gtk::ResponseType::Accept),
+        ],
+    );
+
+    let content_area = dialog.content_area();
+    content_area.set_margin_start(12);
+    content_area.set_margin_end(12);
+    content_area.set_margin_top(12);
+    content_area.set_margin_bottom(12);
+
+    let label = gtk::Label::new(Some("Name:"));
+    label.set_halign(gtk::Align::Start);
+    content_area.append(&label);
+
+    let entry = gtk::Entry::new();
+    entry.set_text(suggested_name);
+    entry.set_activates_default(true);
+    content_area.append(&entry);
+
+    dialog.set_default_response(gtk::ResponseType::Accept);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            let name = entry.text().trim().to_string();
+            if !name.is_empty() {
+                on_save(name);
+            }
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
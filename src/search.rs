@@ -1,13 +1,185 @@
+use crate::recursive_search::WalkOptions;
 use gtk::prelude::*;
-use gtk::{Orientation, SearchEntry};
+use gtk::{CheckButton, ComboBoxText, Orientation, SearchEntry, ToggleButton};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::rc::Rc;
 
+/// How the search text is matched against file names.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Case-insensitive substring match.
+    Substring,
+    /// Shell-style pattern such as `*.rs`, matched against the whole name.
+    Glob,
+    /// Regular expression, matched anywhere in the name.
+    Regex,
+}
+
+/// A compiled name pattern.
+#[derive(Clone, Debug)]
+pub enum NameMatcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl NameMatcher {
+    pub fn new(pattern: &str, mode: SearchMode) -> Result<Self, regex::Error> {
+        match mode {
+            SearchMode::Substring => Ok(NameMatcher::Substring(pattern.to_lowercase())),
+            SearchMode::Glob => RegexBuilder::new(&glob_to_regex(pattern))
+                .case_insensitive(true)
+                .build()
+                .map(NameMatcher::Regex),
+            SearchMode::Regex => RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(NameMatcher::Regex),
+        }
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            NameMatcher::Substring(needle) => name.to_lowercase().contains(needle.as_str()),
+            NameMatcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl SearchMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SearchMode::Substring => "substring",
+            SearchMode::Glob => "glob",
+            SearchMode::Regex => "regex",
+        }
+    }
+}
+
+/// Everything the search bar is set to, so a search can be saved and re-run.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchState {
+    pub text: String,
+    pub mode: SearchMode,
+    pub recursive: bool,
+    pub contents: bool,
+    pub walk_options: WalkOptions,
+}
+
+impl Default for SearchState {
+    fn default() -> Self {
+        SearchState {
+            text: String::new(),
+            mode: SearchMode::Substring,
+            recursive: false,
+            contents: false,
+            walk_options: WalkOptions::default(),
+        }
+    }
+}
+
+/// Builds the case-insensitive pattern used to search file contents. Only
+/// `SearchMode::Regex` is interpreted; other modes search for the literal text.
+pub fn content_regex(pattern: &str, mode: SearchMode) -> Result<Regex, regex::Error> {
+    let pattern = match mode {
+        SearchMode::Regex => pattern.to_string(),
+        SearchMode::Substring | SearchMode::Glob => regex::escape(pattern),
+    };
+    RegexBuilder::new(&pattern).case_insensitive(true).build()
+}
+
+/// Translates a glob (`*`, `?`, `[...]`) into an anchored regular expression.
+pub fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::from("^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                if let Some((class, end)) = glob_class(&chars, i) {
+                    regex.push_str(&class);
+                    i = end;
+                    continue;
+                }
+                // Unterminated class: the bracket is literal
+                regex.push_str("\\[");
+            }
+            c => push_literal(&mut regex, c),
+        }
+        i += 1;
+    }
+    regex.push('$');
+    regex
+}
+
+/// Translates the bracket expression opening at `chars[start]` into a regex
+/// class, returning it with the index just past its closing `]`. `[!...]`
+/// and `[^...]` negate, and a `]` right after the opening bracket (or its
+/// negation) is a member rather than the end. `None` if it is unterminated.
+pub(crate) fn glob_class(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut class = String::from("[");
+    let mut i = start + 1;
+    if matches!(chars.get(i), Some('!' | '^')) {
+        class.push('^');
+        i += 1;
+    }
+    if chars.get(i) == Some(&']') {
+        class.push_str("\\]");
+        i += 1;
+    }
+    while let Some(&c) = chars.get(i) {
+        match c {
+            ']' => {
+                class.push(']');
+                return Some((class, i + 1));
+            }
+            // Special inside regex classes (nesting and set operations)
+            '\\' | '[' | '^' | '&' | '~' => {
+                class.push('\\');
+                class.push(c);
+            }
+            _ => class.push(c),
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Appends `c` to `regex`, escaped if it means something there.
+pub(crate) fn push_literal(regex: &mut String, c: char) {
+    if regex_syntax_char(c) {
+        regex.push('\\');
+    }
+    regex.push(c);
+}
+
+fn regex_syntax_char(c: char) -> bool {
+    matches!(
+        c,
+        '\\' | '^' | '$' | '.' | '|' | '+' | '*' | '?' | '(' | ')' | '{' | '}' | '[' | ']'
+    )
+}
+
 #[derive(Clone)]
 pub struct SearchBar {
     widget: gtk::Box,
     search_entry: SearchEntry,
+    mode_combo: ComboBoxText,
+    recursive_button: ToggleButton,
+    contents_button: ToggleButton,
+    skip_hidden_check: CheckButton,
+    follow_symlinks_check: CheckButton,
+    same_filesystem_check: CheckButton,
+    gitignore_check: CheckButton,
     on_search: Rc<RefCell<Option<Rc<dyn Fn(String)>>>>,
+    /// Text put in the entry by `set_state`, whose delayed `search-changed`
+    /// must not search a second time.
+    restored_text: Rc<RefCell<Option<String>>>,
 }
 
 impl SearchBar {
@@ -20,15 +192,96 @@ impl SearchBar {
         widget.set_visible(false);
 
         let search_entry = SearchEntry::new();
-        search_entry.set_placeholder_text(Some("Search files..."));
+        search_entry.set_placeholder_text(Some(
+            "Search files, e.g. report type:document size:>1M modified:<7d",
+        ));
         search_entry.set_hexpand(true);
 
         widget.append(&search_entry);
 
+        let mode_combo = ComboBoxText::new();
+        mode_combo.append(Some(SearchMode::Substring.as_str()), "Name contains");
+        mode_combo.append(Some(SearchMode::Glob.as_str()), "Glob pattern");
+        mode_combo.append(Some(SearchMode::Regex.as_str()), "Regular expression");
+        mode_combo.set_active_id(Some(SearchMode::Substring.as_str()));
+        widget.append(&mode_combo);
+
+        // Recursive search and its options
+        let recursive_button = ToggleButton::builder()
+            .icon_name("folder-saved-search-symbolic")
+            .tooltip_text("Search in subfolders")
+            .build();
+        widget.append(&recursive_button);
+
+        // Content search always covers subfolders
+        let contents_button = ToggleButton::builder()
+            .icon_name("edit-find-replace-symbolic")
+            .tooltip_text("Search file contents")
+            .build();
+        widget.append(&contents_button);
+
+        let defaults = WalkOptions::default();
+        let skip_hidden_check = CheckButton::with_label("Skip hidden folders");
+        skip_hidden_check.set_active(defaults.skip_hidden);
+        let follow_symlinks_check = CheckButton::with_label("Follow symbolic links");
+        follow_symlinks_check.set_active(defaults.follow_symlinks);
+        let same_filesystem_check = CheckButton::with_label("Stay on this file system");
+        same_filesystem_check.set_active(defaults.same_filesystem);
+        let gitignore_check = CheckButton::with_label("Respect .gitignore");
+        gitignore_check.set_active(defaults.respect_gitignore);
+
+        let options_box = gtk::Box::new(Orientation::Vertical, 6);
+        options_box.set_margin_start(6);
+        options_box.set_margin_end(6);
+        options_box.set_margin_top(6);
+        options_box.set_margin_bottom(6);
+        options_box.append(&skip_hidden_check);
+        options_box.append(&follow_symlinks_check);
+        options_box.append(&same_filesystem_check);
+        options_box.append(&gitignore_check);
+
+        let options_popover = gtk::Popover::new();
+        options_popover.set_child(Some(&options_box));
+        let options_button = gtk::MenuButton::builder()
+            .icon_name("emblem-system-symbolic")
+            .tooltip_text("Subfolder search options")
+            .popover(&options_popover)
+            .build();
+        options_button.set_sensitive(false);
+        let update_sensitivity = {
+            let recursive_button = recursive_button.clone();
+            let contents_button = contents_button.clone();
+            let options_button = options_button.clone();
+            move || {
+                let contents = contents_button.is_active();
+                recursive_button.set_sensitive(!contents);
+                options_button.set_sensitive(contents || recursive_button.is_active());
+            }
+        };
+        let update = update_sensitivity.clone();
+        recursive_button.connect_toggled(move |_| update());
+        contents_button.connect_toggled(move |_| update_sensitivity());
+        widget.append(&options_button);
+
+        let save_button = gtk::Button::builder()
+            .icon_name("document-save-symbolic")
+            .tooltip_text("Save search")
+            .action_name("win.save-search")
+            .build();
+        widget.append(&save_button);
+
         let search_bar = SearchBar {
             widget,
             search_entry,
+            mode_combo,
+            recursive_button,
+            contents_button,
+            skip_hidden_check,
+            follow_symlinks_check,
+            same_filesystem_check,
+            gitignore_check,
             on_search: Rc::new(RefCell::new(None)),
+            restored_text: Rc::new(RefCell::new(None)),
         };
 
         search_bar.setup_signals();
@@ -55,8 +308,12 @@ impl SearchBar {
 
     fn setup_signals(&self) {
         let on_search = self.on_search.clone();
+        let restored_text = self.restored_text.clone();
         self.search_entry.connect_search_changed(move |entry| {
             let text = entry.text().to_string();
+            if restored_text.borrow_mut().take().as_ref() == Some(&text) {
+                return;
+            }
             if let Some(callback) = on_search.borrow().as_ref() {
                 callback(text);
             }
@@ -69,9 +326,117 @@ impl SearchBar {
                 callback(text);
             }
         });
+
+        let on_search = self.on_search.clone();
+        let search_entry = self.search_entry.clone();
+        self.mode_combo.connect_changed(move |_| {
+            let text = search_entry.text().to_string();
+            if let Some(callback) = on_search.borrow().as_ref() {
+                callback(text);
+            }
+        });
+
+        // Changing where to search re-runs the search
+        let toggles = [
+            self.recursive_button.clone().upcast::<gtk::Widget>(),
+            self.contents_button.clone().upcast(),
+            self.skip_hidden_check.clone().upcast(),
+            self.follow_symlinks_check.clone().upcast(),
+            self.same_filesystem_check.clone().upcast(),
+            self.gitignore_check.clone().upcast(),
+        ];
+        for toggle in toggles {
+            let on_search = self.on_search.clone();
+            let search_entry = self.search_entry.clone();
+            toggle.connect_notify_local(Some("active"), move |_, _| {
+                let text = search_entry.text().to_string();
+                if let Some(callback) = on_search.borrow().as_ref() {
+                    callback(text);
+                }
+            });
+        }
+    }
+
+    /// Whether the search should descend into subfolders.
+    pub fn is_recursive(&self) -> bool {
+        self.recursive_button.is_active()
+    }
+
+    /// Whether the search looks inside files rather than at their names.
+    pub fn searches_contents(&self) -> bool {
+        self.contents_button.is_active()
+    }
+
+    pub fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            skip_hidden: self.skip_hidden_check.is_active(),
+            follow_symlinks: self.follow_symlinks_check.is_active(),
+            same_filesystem: self.same_filesystem_check.is_active(),
+            respect_gitignore: self.gitignore_check.is_active(),
+        }
+    }
+
+    pub fn mode(&self) -> SearchMode {
+        match self.mode_combo.active_id().as_deref() {
+            Some("glob") => SearchMode::Glob,
+            Some("regex") => SearchMode::Regex,
+            _ => SearchMode::Substring,
+        }
+    }
+
+    pub fn state(&self) -> SearchState {
+        SearchState {
+            text: self.get_text(),
+            mode: self.mode(),
+            recursive: self.is_recursive(),
+            contents: self.searches_contents(),
+            walk_options: self.walk_options(),
+        }
+    }
+
+    /// Restores a saved state and searches once with it.
+    pub fn set_state(&self, state: &SearchState) {
+        // Keep the individual changes from each starting a search
+        let callback = self.on_search.borrow_mut().take();
+
+        self.mode_combo.set_active_id(Some(state.mode.as_str()));
+        self.recursive_button.set_active(state.recursive);
+        self.contents_button.set_active(state.contents);
+        self.skip_hidden_check
+            .set_active(state.walk_options.skip_hidden);
+        self.follow_symlinks_check
+            .set_active(state.walk_options.follow_symlinks);
+        self.same_filesystem_check
+            .set_active(state.walk_options.same_filesystem);
+        self.gitignore_check
+            .set_active(state.walk_options.respect_gitignore);
+        // The entry reports the change after a delay, once the callback is
+        // back; skip that report as the search runs right below
+        if self.search_entry.text() != state.text {
+            *self.restored_text.borrow_mut() = Some(state.text.clone());
+            self.search_entry.set_text(&state.text);
+        }
+
+        *self.on_search.borrow_mut() = callback.clone();
+        if let Some(callback) = callback {
+            callback(state.text.clone());
+        }
+    }
+
+    /// Marks the entry as invalid, showing `message` as its tooltip.
+    pub fn set_error(&self, message: Option<&str>) {
+        match message {
+            Some(message) => {
+                self.search_entry.add_css_class("error");
+                self.search_entry.set_tooltip_text(Some(message));
+            }
+            None => {
+                self.search_entry.remove_css_class("error");
+                self.search_entry.set_tooltip_text(None);
+            }
+        }
     }
 
-    #[allow(unused)]
     pub fn connect_search<F>(&self, callback: F)
     where
         F: Fn(String) + 'static,
@@ -79,7 +444,6 @@ impl SearchBar {
         *self.on_search.borrow_mut() = Some(Rc::new(callback));
     }
 
-    #[allow(unused)]
     pub fn get_text(&self) -> String {
         self.search_entry.text().to_string()
     }
@@ -95,3 +459,58 @@ impl Default for SearchBar {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_matches(glob: &str, name: &str) -> bool {
+        Regex::new(&glob_to_regex(glob)).unwrap().is_match(name)
+    }
+
+    #[test]
+    fn translates_wildcards() {
+        assert!(glob_matches("*.rs", "main.rs"));
+        assert!(glob_matches("*.rs", ".rs"));
+        assert!(!glob_matches("*.rs", "main.rs.bak"));
+        assert!(glob_matches("?.txt", "a.txt"));
+        assert!(!glob_matches("?.txt", "ab.txt"));
+    }
+
+    #[test]
+    fn escapes_regex_syntax() {
+        assert!(glob_matches("a+b (1).txt", "a+b (1).txt"));
+        assert!(!glob_matches("a.txt", "abtxt"));
+        assert!(glob_matches("$HOME^{x}|y", "$HOME^{x}|y"));
+        assert!(glob_matches("back\\slash", "back\\slash"));
+    }
+
+    #[test]
+    fn translates_classes() {
+        assert!(glob_matches("[abc].txt", "b.txt"));
+        assert!(!glob_matches("[abc].txt", "d.txt"));
+        assert!(glob_matches("file[0-9]", "file7"));
+        assert!(glob_matches("[!abc]", "d"));
+        assert!(!glob_matches("[!abc]", "a"));
+        assert!(glob_matches("[^abc]", "d"));
+        assert!(glob_matches("[a&&b]", "&"));
+        assert!(glob_matches("[[]", "["));
+    }
+
+    #[test]
+    fn treats_a_leading_bracket_in_a_class_as_a_member() {
+        assert_eq!(glob_to_regex("[]]"), "^[\\]]$");
+        assert!(glob_matches("[]]", "]"));
+        assert!(!glob_matches("[]]", "a"));
+        assert!(glob_matches("[]a]", "a"));
+        assert!(glob_matches("[!]]", "a"));
+        assert!(!glob_matches("[!]]", "]"));
+    }
+
+    #[test]
+    fn keeps_unterminated_brackets_literal() {
+        assert!(glob_matches("[abc", "[abc"));
+        assert!(glob_matches("*[", "file["));
+        assert!(glob_matches("[!]", "[!]"));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::bookmarks::Bookmarks;
+use crate::devices::Devices;
+use crate::file_index::FileIndex;
+use crate::folder_size::FolderSizeCache;
+use crate::folder_views::FolderViewStore;
+use crate::frecency::FrecencyStore;
+use crate::saved_searches::SavedSearchStore;
+use crate::settings::SettingsStore;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Services shared by every window of the application.
+#[derive(Clone)]
+pub struct AppServices {
+    pub bookmarks: Bookmarks,
+    pub clipboard: Clipboard,
+    pub devices: Devices,
+    pub file_index: FileIndex,
+    pub folder_sizes: FolderSizeCache,
+    pub folder_views: FolderViewStore,
+    pub jobs: JobQueue,
+    pub locations: FrecencyStore,
+    pub saved_searches: SavedSearchStore,
+    pub settings: SettingsStore,
+}
+
+impl AppServices {
+    pub fn new() -> Self {
+        AppServices {
+            bookmarks: Bookmarks::load(),
+            clipboard: Clipboard::new(),
+            devices: Devices::new(),
+            file_index: FileIndex::new(),
+            folder_sizes: FolderSizeCache::new(),
+            folder_views: FolderViewStore::load(),
+            jobs: JobQueue::new(),
+            locations: FrecencyStore::load(),
+            saved_searches: SavedSearchStore::load(),
+            settings: SettingsStore::load(),
+        }
+    }
+}
+
+impl Default for AppServices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type Handlers<A> = Vec<(usize, Rc<dyn Fn(&A)>)>;
+
+/// Callbacks connected to one of the shared stores, each called with `A`
+/// whenever the store changes.
+pub struct ChangeHandlers<A = ()> {
+    handlers: Rc<RefCell<Handlers<A>>>,
+    next_id: Rc<Cell<usize>>,
+}
+
+impl<A> ChangeHandlers<A> {
+    pub fn new() -> Self {
+        ChangeHandlers {
+            handlers: Rc::new(RefCell::new(Vec::new())),
+            next_id: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Returns an id to pass to `disconnect`.
+    pub fn connect<F>(&self, callback: F) -> usize
+    where
+        F: Fn(&A) + 'static,
+    {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.handlers.borrow_mut().push((id, Rc::new(callback)));
+        id
+    }
+
+    pub fn disconnect(&self, handler_id: usize) {
+        self.handlers
+            .borrow_mut()
+            .retain(|(id, _)| *id != handler_id);
+    }
+
+    /// Handlers may connect or disconnect others while being notified.
+    pub fn notify(&self, value: &A) {
+        let handlers: Vec<_> = self
+            .handlers
+            .borrow()
+            .iter()
+            .map(|(_, handler)| handler.clone())
+            .collect();
+        for handler in handlers {
+            handler(value);
+        }
+    }
+}
+
+// derived Clone and Default would require `A: Clone + Default`
+impl<A> Clone for ChangeHandlers<A> {
+    fn clone(&self) -> Self {
+        ChangeHandlers {
+            handlers: self.handlers.clone(),
+            next_id: self.next_id.clone(),
+        }
+    }
+}
+
+impl<A> Default for ChangeHandlers<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClipboardOperation {
+    Copy,
+    Cut,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ClipboardContents {
+    pub operation: ClipboardOperation,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Files that were copied or cut in one window and can be pasted in any other.
+#[derive(Clone)]
+pub struct Clipboard {
+    contents: Rc<RefCell<Option<ClipboardContents>>>,
+}
+
+impl Clipboard {
+    fn new() -> Self {
+        Clipboard {
+            contents: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn set(&self, operation: ClipboardOperation, paths: Vec<PathBuf>) {
+        *self.contents.borrow_mut() = Some(ClipboardContents { operation, paths });
+    }
+
+    pub fn get(&self) -> Option<ClipboardContents> {
+        self.contents.borrow().clone()
+    }
+
+    /// Clears `pasted` once its files were moved, since they cannot be moved
+    /// twice. Copied files, or anything put on the clipboard since, stay.
+    pub fn finish_paste(&self, pasted: &ClipboardContents) {
+        let mut contents = self.contents.borrow_mut();
+        if pasted.operation == ClipboardOperation::Cut && contents.as_ref() == Some(pasted) {
+            *contents = None;
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce() -> Result<(), String> + Send>;
+type JobDone = Box<dyn FnOnce(Result<(), String>)>;
+
+/// Runs long file operations one at a time off the main thread.
+#[derive(Clone)]
+pub struct JobQueue {
+    pending: Rc<RefCell<VecDeque<(Job, JobDone)>>>,
+    running: Rc<Cell<bool>>,
+    on_changed: ChangeHandlers<usize>,
+}
+
+impl JobQueue {
+    fn new() -> Self {
+        JobQueue {
+            pending: Rc::new(RefCell::new(VecDeque::new())),
+            running: Rc::new(Cell::new(false)),
+            on_changed: ChangeHandlers::new(),
+        }
+    }
+
+    /// Queues `job` to run on a worker thread; `on_done` is called on the main
+    /// thread with its result.
+    pub fn push<J, D>(&self, job: J, on_done: D)
+    where
+        J: FnOnce() -> Result<(), String> + Send + 'static,
+        D: FnOnce(Result<(), String>) + 'static,
+    {
+        self.pending
+            .borrow_mut()
+            .push_back((Box::new(job), Box::new(on_done)));
+        self.notify_changed();
+
+        if !self.running.get() {
+            self.run_next();
+        }
+    }
+
+    /// Number of queued jobs, including the one currently running.
+    pub fn active_count(&self) -> usize {
+        self.pending.borrow().len() + usize::from(self.running.get())
+    }
+
+    pub fn connect_changed<F>(&self, callback: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        self.on_changed.connect(move |count| callback(*count));
+    }
+
+    fn run_next(&self) {
+        let Some((job, on_done)) = self.pending.borrow_mut().pop_front() else {
+            return;
+        };
+        self.running.set(true);
+
+        let queue = self.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(job)
+                .await
+                .unwrap_or_else(|_| Err("File operation panicked".to_string()));
+
+            queue.running.set(false);
+            on_done(result);
+            queue.notify_changed();
+            queue.run_next();
+        });
+    }
+
+    fn notify_changed(&self) {
+        self.on_changed.notify(&self.active_count());
+    }
+}
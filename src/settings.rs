@@ -0,0 +1,214 @@
+use crate::file_info::{SizeFormat, SizeUnits};
+use crate::file_view::{ListColumn, SortColumn, ViewMode, ViewOptions, DEFAULT_ZOOM_LEVEL};
+use crate::services::ChangeHandlers;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultView {
+    Grid,
+    List,
+}
+
+/// User preferences, persisted to `$XDG_CONFIG_HOME/zfile/settings.json`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub show_hidden: bool,
+    pub hide_backup_files: bool,
+    pub default_view: DefaultView,
+    pub default_sort_column: SortColumn,
+    pub default_sort_descending: bool,
+    pub default_zoom_level: u32,
+    pub single_click: bool,
+    pub confirm_delete: bool,
+    pub show_thumbnails: bool,
+    pub size_units: SizeUnits,
+    /// Show space taken on disk rather than the apparent size.
+    pub show_allocated_size: bool,
+    /// Add up folder sizes in the background, for list view and sorting.
+    pub folder_sizes: bool,
+    /// Optional columns shown in list view.
+    pub list_columns: Vec<ListColumn>,
+    pub index_enabled: bool,
+    pub indexed_roots: Vec<PathBuf>,
+    /// Names (globs) or absolute folders left out of the index.
+    pub index_exclusions: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            show_hidden: false,
+            hide_backup_files: true,
+            default_view: DefaultView::Grid,
+            default_sort_column: SortColumn::Name,
+            default_sort_descending: false,
+            default_zoom_level: DEFAULT_ZOOM_LEVEL,
+            single_click: false,
+            confirm_delete: true,
+            show_thumbnails: true,
+            size_units: SizeUnits::Decimal,
+            show_allocated_size: false,
+            folder_sizes: false,
+            list_columns: Vec::new(),
+            index_enabled: false,
+            indexed_roots: dirs::home_dir().into_iter().collect(),
+            index_exclusions: vec![
+                "node_modules".to_string(),
+                "target".to_string(),
+                "__pycache__".to_string(),
+            ],
+        }
+    }
+}
+
+impl Settings {
+    /// View options for folders that have no settings of their own.
+    pub fn default_view_options(&self) -> ViewOptions {
+        ViewOptions {
+            mode: match self.default_view {
+                DefaultView::Grid => ViewMode::Grid,
+                DefaultView::List => ViewMode::List,
+            },
+            sort_column: self.default_sort_column,
+            sort_descending: self.default_sort_descending,
+            zoom_level: self.default_zoom_level,
+            show_hidden: self.show_hidden,
+        }
+    }
+
+    pub fn size_format(&self) -> SizeFormat {
+        SizeFormat {
+            units: self.size_units,
+            allocated: self.show_allocated_size,
+        }
+    }
+
+    pub fn set_default_view_options(&mut self, options: ViewOptions) {
+        self.default_view = match options.mode {
+            ViewMode::Grid => DefaultView::Grid,
+            ViewMode::List => DefaultView::List,
+        };
+        self.default_sort_column = options.sort_column;
+        self.default_sort_descending = options.sort_descending;
+        self.default_zoom_level = options.zoom_level;
+        self.show_hidden = options.show_hidden;
+    }
+}
+
+/// Directory holding zfile's configuration files.
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("zfile")
+}
+
+/// Directory holding what zfile collects as it runs: the file index,
+/// visited folders and per-folder views. Without a home folder both fall back
+/// to the temporary folder rather than the working directory.
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir().unwrap_or_else(env::temp_dir).join("zfile")
+}
+
+/// Reads a JSON file written by `save_json`. A missing file is not an
+/// error; one that doesn't parse is reported as `what` and ignored.
+pub fn load_json<T: DeserializeOwned>(path: &Path, what: &str) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents)
+        .map_err(|e| eprintln!("Failed to parse {}: {}", what, e))
+        .ok()
+}
+
+/// Writes `value` to `path` as JSON, creating the folder if needed. Failures
+/// are reported as `what`.
+pub fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T, what: &str) {
+    if let Err(e) = write_json(path, value) {
+        eprintln!("Failed to save {}: {}", what, e);
+    }
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string(value)?;
+    fs::write(path, contents)
+}
+
+fn settings_path() -> PathBuf {
+    config_dir().join("settings.json")
+}
+
+/// Shared, observable settings. Every change is written to disk and then
+/// broadcast to the connected handlers.
+#[derive(Clone)]
+pub struct SettingsStore {
+    settings: Rc<RefCell<Settings>>,
+    handlers: ChangeHandlers<Settings>,
+}
+
+impl SettingsStore {
+    pub fn load() -> Self {
+        let settings = match fs::read_to_string(settings_path()) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Failed to parse settings: {}", e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        };
+
+        SettingsStore {
+            settings: Rc::new(RefCell::new(settings)),
+            handlers: ChangeHandlers::new(),
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.borrow().clone()
+    }
+
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Settings),
+    {
+        let settings = {
+            let mut settings = self.settings.borrow_mut();
+            let previous = settings.clone();
+            f(&mut settings);
+            if *settings == previous {
+                return;
+            }
+            settings.clone()
+        };
+
+        if let Err(e) = save(&settings) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+
+        self.handlers.notify(&settings);
+    }
+
+    pub fn connect_changed<F>(&self, callback: F) -> usize
+    where
+        F: Fn(&Settings) + 'static,
+    {
+        self.handlers.connect(callback)
+    }
+
+    pub fn disconnect(&self, handler_id: usize) {
+        self.handlers.disconnect(handler_id);
+    }
+}
+
+fn save(settings: &Settings) -> std::io::Result<()> {
+    fs::create_dir_all(config_dir())?;
+    let contents = serde_json::to_string_pretty(settings)?;
+    fs::write(settings_path(), contents)
+}
@@ -1,19 +1,66 @@
+use crate::bookmarks::{self, Bookmark, Bookmarks};
+use crate::devices::{Device, Devices};
+use crate::file_info;
+use crate::saved_searches::SavedSearch;
+use crate::services::AppServices;
+use crate::settings::SettingsStore;
 use gtk::prelude::*;
 use gtk::{Label, ListBox, Orientation};
-use std::cell::RefCell;
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+/// Special folders listed under Places, in order.
+const USER_DIRECTORIES: [(glib::UserDirectory, &str); 8] = [
+    (glib::UserDirectory::Desktop, "user-desktop-symbolic"),
+    (glib::UserDirectory::Documents, "folder-documents-symbolic"),
+    (glib::UserDirectory::Downloads, "folder-download-symbolic"),
+    (glib::UserDirectory::Music, "folder-music-symbolic"),
+    (glib::UserDirectory::Pictures, "folder-pictures-symbolic"),
+    (glib::UserDirectory::Videos, "folder-videos-symbolic"),
+    (glib::UserDirectory::Templates, "folder-templates-symbolic"),
+    (
+        glib::UserDirectory::PublicShare,
+        "folder-publicshare-symbolic",
+    ),
+];
+
+/// An entry in the Places section.
+#[derive(Clone, PartialEq)]
+enum Place {
+    /// Recently used files, shared with other applications.
+    Recent,
+    Folder(PathBuf),
+}
+
 #[derive(Clone)]
 pub struct Sidebar {
     widget: gtk::Box,
     list_box: ListBox,
+    bookmarks_label: Label,
+    bookmarks_list_box: ListBox,
+    devices_label: Label,
+    devices_list_box: ListBox,
+    saved_label: Label,
+    saved_list_box: ListBox,
     on_location_activated: Rc<RefCell<Option<Rc<dyn Fn(PathBuf)>>>>,
-    locations: Rc<RefCell<Vec<PathBuf>>>,
+    on_recent_activated: Rc<RefCell<Option<Rc<dyn Fn()>>>>,
+    on_saved_search_activated: Rc<RefCell<Option<Rc<dyn Fn(SavedSearch)>>>>,
+    on_saved_search_removed: Rc<RefCell<Option<Rc<dyn Fn(String)>>>>,
+    places: Rc<RefCell<Vec<Place>>>,
+    current_place: Rc<RefCell<Option<Place>>>,
+    bookmark_store: Bookmarks,
+    bookmarks: Rc<RefCell<Vec<(Bookmark, PathBuf)>>>,
+    device_monitor: Devices,
+    devices: Rc<RefCell<Vec<Device>>>,
+    settings: SettingsStore,
+    saved_searches: Rc<RefCell<Vec<SavedSearch>>>,
+    /// Handlers on the shared bookmarks and devices, which hold the sidebar.
+    store_handlers: Rc<Cell<Option<(usize, usize)>>>,
 }
 
 impl Sidebar {
-    pub fn new() -> Self {
+    pub fn new(services: &AppServices) -> Self {
         let widget = gtk::Box::new(Orientation::Vertical, 0);
 
         // Places section
@@ -32,112 +79,517 @@ impl Sidebar {
 
         widget.append(&list_box);
 
+        // Bookmarks section, shared with the GTK file chooser
+        let bookmarks_label = Label::new(Some("Bookmarks"));
+        bookmarks_label.set_halign(gtk::Align::Start);
+        bookmarks_label.set_margin_start(12);
+        bookmarks_label.set_margin_end(12);
+        bookmarks_label.set_margin_top(12);
+        bookmarks_label.set_margin_bottom(6);
+        bookmarks_label.add_css_class("heading");
+        widget.append(&bookmarks_label);
+
+        let bookmarks_list_box = ListBox::new();
+        bookmarks_list_box.set_selection_mode(gtk::SelectionMode::Single);
+        bookmarks_list_box.add_css_class("navigation-sidebar");
+        widget.append(&bookmarks_list_box);
+
+        // Devices section, hidden while there are none
+        let devices_label = Label::new(Some("Devices"));
+        devices_label.set_halign(gtk::Align::Start);
+        devices_label.set_margin_start(12);
+        devices_label.set_margin_end(12);
+        devices_label.set_margin_top(12);
+        devices_label.set_margin_bottom(6);
+        devices_label.add_css_class("heading");
+        widget.append(&devices_label);
+
+        let devices_list_box = ListBox::new();
+        devices_list_box.set_selection_mode(gtk::SelectionMode::Single);
+        devices_list_box.add_css_class("navigation-sidebar");
+        widget.append(&devices_list_box);
+
+        // Saved searches section, hidden while there are none
+        let saved_label = Label::new(Some("Saved Searches"));
+        saved_label.set_halign(gtk::Align::Start);
+        saved_label.set_margin_start(12);
+        saved_label.set_margin_end(12);
+        saved_label.set_margin_top(12);
+        saved_label.set_margin_bottom(6);
+        saved_label.add_css_class("heading");
+        saved_label.set_visible(false);
+        widget.append(&saved_label);
+
+        let saved_list_box = ListBox::new();
+        saved_list_box.set_selection_mode(gtk::SelectionMode::Single);
+        saved_list_box.add_css_class("navigation-sidebar");
+        saved_list_box.set_visible(false);
+        widget.append(&saved_list_box);
+
         let sidebar = Sidebar {
             widget,
             list_box,
+            bookmarks_label,
+            bookmarks_list_box,
+            devices_label,
+            devices_list_box,
+            saved_label,
+            saved_list_box,
             on_location_activated: Rc::new(RefCell::new(None)),
-            locations: Rc::new(RefCell::new(Vec::new())),
+            on_recent_activated: Rc::new(RefCell::new(None)),
+            on_saved_search_activated: Rc::new(RefCell::new(None)),
+            on_saved_search_removed: Rc::new(RefCell::new(None)),
+            places: Rc::new(RefCell::new(Vec::new())),
+            current_place: Rc::new(RefCell::new(None)),
+            bookmark_store: services.bookmarks.clone(),
+            bookmarks: Rc::new(RefCell::new(Vec::new())),
+            device_monitor: services.devices.clone(),
+            devices: Rc::new(RefCell::new(Vec::new())),
+            settings: services.settings.clone(),
+            saved_searches: Rc::new(RefCell::new(Vec::new())),
+            store_handlers: Rc::new(Cell::new(None)),
         };
 
         sidebar.populate_places();
+        sidebar.populate_bookmarks();
+        sidebar.populate_devices();
         sidebar.setup_activation();
 
+        let sidebar_clone = sidebar.clone();
+        let bookmarks_handler = services
+            .bookmarks
+            .connect_changed(move || sidebar_clone.populate_bookmarks());
+        let sidebar_clone = sidebar.clone();
+        let devices_handler = services
+            .devices
+            .connect_changed(move || sidebar_clone.populate_devices());
+        sidebar
+            .store_handlers
+            .set(Some((bookmarks_handler, devices_handler)));
+
         sidebar
     }
 
+    /// Stops following the shared bookmarks and devices. Their handlers keep
+    /// the sidebar alive, so the window calls this when it is destroyed.
+    pub fn disconnect(&self) {
+        if let Some((bookmarks_handler, devices_handler)) = self.store_handlers.take() {
+            self.bookmark_store.disconnect(bookmarks_handler);
+            self.device_monitor.disconnect(devices_handler);
+        }
+    }
+
     pub fn widget(&self) -> gtk::Box {
         self.widget.clone()
     }
 
+    /// Recent files, home, the XDG user directories from `user-dirs.dirs`,
+    /// then the root. Unset user directories point at home and are left out.
     fn populate_places(&self) {
-        let mut locations = self.locations.borrow_mut();
+        let mut places = self.places.borrow_mut();
+        let home_dir = dirs::home_dir();
+
+        places.push(Place::Recent);
+        let row = self.create_place_row("Recent", "document-open-recent-symbolic");
+        self.list_box.append(&row);
 
-        // Home
-        if let Some(home_dir) = dirs::home_dir() {
-            locations.push(home_dir.clone());
+        if let Some(home_dir) = &home_dir {
+            places.push(Place::Folder(home_dir.clone()));
             let row = self.create_place_row("Home", "user-home-symbolic");
             self.list_box.append(&row);
         }
 
-        // Desktop
-        if let Some(home_dir) = dirs::home_dir() {
-            let desktop_dir = home_dir.join("Desktop");
-            if desktop_dir.exists() {
-                locations.push(desktop_dir.clone());
-                let row = self.create_place_row("Desktop", "user-desktop-symbolic");
-                self.list_box.append(&row);
+        for (directory, icon_name) in USER_DIRECTORIES {
+            let Some(path) = glib::user_special_dir(directory) else {
+                continue;
+            };
+            if home_dir.as_ref() == Some(&path) || !path.is_dir() {
+                continue;
             }
+            // The folder name is already translated by xdg-user-dirs-update
+            let name = glib::filename_display_basename(&path);
+            places.push(Place::Folder(path));
+            let row = self.create_place_row(&name, icon_name);
+            self.list_box.append(&row);
         }
 
-        // Documents
-        if let Some(documents_dir) = dirs::document_dir() {
-            locations.push(documents_dir.clone());
-            let row = self.create_place_row("Documents", "folder-documents-symbolic");
-            self.list_box.append(&row);
+        places.push(Place::Folder(PathBuf::from("/")));
+        let row = self.create_place_row("Root", "drive-harddisk-symbolic");
+        self.list_box.append(&row);
+    }
+
+    fn create_place_row(&self, name: &str, icon_name: &str) -> gtk::Box {
+        let row = gtk::Box::new(Orientation::Horizontal, 12);
+        row.set_margin_start(12);
+        row.set_margin_end(12);
+        row.set_margin_top(6);
+        row.set_margin_bottom(6);
+
+        let icon = gtk::Image::from_icon_name(icon_name);
+        icon.set_pixel_size(16);
+        row.append(&icon);
+
+        let label = Label::new(Some(name));
+        label.set_halign(gtk::Align::Start);
+        label.set_hexpand(true);
+        row.append(&label);
+
+        row
+    }
+
+    /// Selects the place, bookmark or device row for `path`, if any, so the
+    /// sidebar follows navigation.
+    pub fn set_current_location(&self, path: &Path) {
+        self.select_place(Place::Folder(path.to_path_buf()));
+    }
+
+    /// Selects the Recent row while recent files are shown.
+    pub fn select_recent(&self) {
+        self.select_place(Place::Recent);
+    }
+
+    fn select_place(&self, current: Place) {
+        let path = match &current {
+            Place::Folder(path) => Some(path.as_path()),
+            Place::Recent => None,
+        };
+        let place = self.places.borrow().iter().position(|p| *p == current);
+        let bookmark = self
+            .bookmarks
+            .borrow()
+            .iter()
+            .position(|(_, p)| Some(p.as_path()) == path);
+        let device = self
+            .devices
+            .borrow()
+            .iter()
+            .position(|device| path.is_some() && device.path.as_deref() == path);
+
+        for (list_box, index) in [
+            (&self.list_box, place),
+            (&self.bookmarks_list_box, bookmark),
+            (&self.devices_list_box, device),
+        ] {
+            match index.and_then(|index| list_box.row_at_index(index as i32)) {
+                Some(row) => list_box.select_row(Some(&row)),
+                None => list_box.unselect_all(),
+            }
         }
+        self.saved_list_box.unselect_all();
+        *self.current_place.borrow_mut() = Some(current);
+    }
 
-        // Downloads
-        if let Some(downloads_dir) = dirs::download_dir() {
-            locations.push(downloads_dir.clone());
-            let row = self.create_place_row("Downloads", "folder-download-symbolic");
-            self.list_box.append(&row);
+    /// Lists the local bookmarks; remote ones are kept in the file but not
+    /// shown.
+    fn populate_bookmarks(&self) {
+        while let Some(child) = self.bookmarks_list_box.first_child() {
+            self.bookmarks_list_box.remove(&child);
         }
 
-        // Music
-        if let Some(music_dir) = dirs::audio_dir() {
-            locations.push(music_dir.clone());
-            let row = self.create_place_row("Music", "folder-music-symbolic");
-            self.list_box.append(&row);
+        let bookmarks: Vec<(Bookmark, PathBuf)> = self
+            .bookmark_store
+            .list()
+            .into_iter()
+            .filter_map(|bookmark| bookmark.path().map(|path| (bookmark, path)))
+            .collect();
+
+        for (bookmark, _) in &bookmarks {
+            let row = self.create_place_row(&bookmark.display_name(), "user-bookmarks-symbolic");
+            row.set_tooltip_text(Some(&bookmark.uri));
+            self.setup_bookmark_row(&row, bookmark);
+            self.bookmarks_list_box.append(&row);
         }
 
-        // Pictures
-        if let Some(pictures_dir) = dirs::picture_dir() {
-            locations.push(pictures_dir.clone());
-            let row = self.create_place_row("Pictures", "folder-pictures-symbolic");
-            self.list_box.append(&row);
+        self.bookmarks_label.set_visible(!bookmarks.is_empty());
+        self.bookmarks_list_box.set_visible(!bookmarks.is_empty());
+        *self.bookmarks.borrow_mut() = bookmarks;
+        self.reselect_current_location();
+    }
+
+    /// Adds the context menu (rename, remove) and drag-to-reorder to a row.
+    fn setup_bookmark_row(&self, row: &gtk::Box, bookmark: &Bookmark) {
+        let actions = gio::SimpleActionGroup::new();
+
+        let rename_action = gio::SimpleAction::new("rename", None);
+        let bookmark_store = self.bookmark_store.clone();
+        let row_weak = row.downgrade();
+        let renamed = bookmark.clone();
+        rename_action.connect_activate(move |_, _| {
+            let Some(window) = row_weak
+                .upgrade()
+                .and_then(|row| row.root())
+                .and_downcast::<gtk::Window>()
+            else {
+                return;
+            };
+            let bookmark_store = bookmark_store.clone();
+            let uri = renamed.uri.clone();
+            bookmarks::show_rename_dialog(&window, &renamed, move |label| {
+                bookmark_store.rename(&uri, label);
+            });
+        });
+        actions.add_action(&rename_action);
+
+        let remove_action = gio::SimpleAction::new("remove", None);
+        let bookmark_store = self.bookmark_store.clone();
+        let uri = bookmark.uri.clone();
+        remove_action.connect_activate(move |_, _| bookmark_store.remove(&uri));
+        actions.add_action(&remove_action);
+
+        row.insert_action_group("bookmark", Some(&actions));
+
+        let menu = gio::Menu::new();
+        menu.append(Some("Rename…"), Some("bookmark.rename"));
+        menu.append(Some("Remove"), Some("bookmark.remove"));
+
+        let click = gtk::GestureClick::new();
+        click.set_button(gdk::BUTTON_SECONDARY);
+        click.connect_pressed(move |gesture, _, x, y| {
+            let Some(row) = gesture.widget() else {
+                return;
+            };
+            let popover = gtk::PopoverMenu::from_model(Some(&menu));
+            popover.set_parent(&row);
+            popover.set_has_arrow(false);
+            popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+            // Unparent once closed so rebuilt rows don't keep stale popovers
+            popover.connect_closed(|popover| {
+                let popover = popover.clone();
+                glib::idle_add_local_once(move || popover.unparent());
+            });
+            popover.popup();
+        });
+        row.add_controller(click);
+
+        // Drag a bookmark onto another to move it there
+        let drag_source = gtk::DragSource::new();
+        drag_source.set_actions(gdk::DragAction::MOVE);
+        let uri = bookmark.uri.clone();
+        drag_source
+            .connect_prepare(move |_, _, _| Some(gdk::ContentProvider::for_value(&uri.to_value())));
+        row.add_controller(drag_source);
+
+        let drop_target = gtk::DropTarget::new(String::static_type(), gdk::DragAction::MOVE);
+        let bookmark_store = self.bookmark_store.clone();
+        let target_uri = bookmark.uri.clone();
+        drop_target.connect_drop(move |_, value, _, _| match value.get::<String>() {
+            Ok(uri) => {
+                bookmark_store.move_to(&uri, &target_uri);
+                true
+            }
+            Err(_) => false,
+        });
+        row.add_controller(drop_target);
+    }
+
+    fn populate_devices(&self) {
+        while let Some(child) = self.devices_list_box.first_child() {
+            self.devices_list_box.remove(&child);
         }
 
-        // Videos
-        if let Some(videos_dir) = dirs::video_dir() {
-            locations.push(videos_dir.clone());
-            let row = self.create_place_row("Videos", "folder-videos-symbolic");
-            self.list_box.append(&row);
+        let devices = self.device_monitor.list();
+        for device in &devices {
+            self.devices_list_box
+                .append(&self.create_device_row(device));
         }
 
-        // Root
-        let root_dir = PathBuf::from("/");
-        locations.push(root_dir.clone());
-        let row = self.create_place_row("Root", "drive-harddisk-symbolic");
-        self.list_box.append(&row);
+        self.devices_label.set_visible(!devices.is_empty());
+        self.devices_list_box.set_visible(!devices.is_empty());
+        *self.devices.borrow_mut() = devices;
+        self.reselect_current_location();
     }
 
-    fn create_place_row(&self, name: &str, icon_name: &str) -> gtk::Box {
+    /// Restores the highlight after a section was rebuilt.
+    fn reselect_current_location(&self) {
+        let current_place = self.current_place.borrow().clone();
+        if let Some(place) = current_place {
+            self.select_place(place);
+        }
+    }
+
+    /// A place row with a free space bar under the name and an unmount or
+    /// eject button.
+    fn create_device_row(&self, device: &Device) -> gtk::Box {
         let row = gtk::Box::new(Orientation::Horizontal, 12);
         row.set_margin_start(12);
         row.set_margin_end(12);
         row.set_margin_top(6);
         row.set_margin_bottom(6);
 
-        let icon = gtk::Image::from_icon_name(icon_name);
+        let icon = match &device.icon {
+            Some(icon) => gtk::Image::from_gicon(icon),
+            None => gtk::Image::from_icon_name("drive-harddisk-symbolic"),
+        };
         icon.set_pixel_size(16);
         row.append(&icon);
 
-        let label = Label::new(Some(name));
+        let text_box = gtk::Box::new(Orientation::Vertical, 4);
+        text_box.set_hexpand(true);
+        text_box.set_valign(gtk::Align::Center);
+
+        let label = Label::new(Some(&device.name));
         label.set_halign(gtk::Align::Start);
-        label.set_hexpand(true);
-        row.append(&label);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        if !device.is_mounted() {
+            label.add_css_class("dim-label");
+        }
+        text_box.append(&label);
+
+        if device.is_mounted() {
+            let usage_bar = gtk::LevelBar::new();
+            usage_bar.set_visible(false);
+            text_box.append(&usage_bar);
+
+            let device = device.clone();
+            let units = self.settings.get().size_units;
+            let row_weak = row.downgrade();
+            glib::spawn_future_local(async move {
+                let Some((free, total)) = device.usage().await.filter(|&(_, total)| total > 0)
+                else {
+                    return;
+                };
+                if row_weak.upgrade().is_none() {
+                    return;
+                }
+                usage_bar.set_value(total.saturating_sub(free) as f64 / total as f64);
+                usage_bar.set_visible(true);
+                usage_bar.set_tooltip_text(Some(&format!(
+                    "{} free of {}",
+                    file_info::format_bytes(free, units),
+                    file_info::format_bytes(total, units)
+                )));
+            });
+        }
+        row.append(&text_box);
+
+        let button = if device.can_eject() {
+            Some((gtk::Button::from_icon_name("media-eject-symbolic"), "Eject"))
+        } else if device.can_unmount() {
+            Some((
+                gtk::Button::from_icon_name("media-eject-symbolic"),
+                "Unmount",
+            ))
+        } else {
+            None
+        };
+        if let Some((button, tooltip)) = button {
+            button.set_tooltip_text(Some(tooltip));
+            button.add_css_class("flat");
+            button.set_valign(gtk::Align::Center);
+            let device = device.clone();
+            button.connect_clicked(move |button| {
+                let Some(window) = button.root().and_downcast::<gtk::Window>() else {
+                    return;
+                };
+                if device.can_eject() {
+                    device.eject(&window);
+                } else {
+                    device.unmount(&window);
+                }
+            });
+            row.append(&button);
+        }
 
         row
     }
 
+    /// Replaces the listed saved searches.
+    pub fn set_saved_searches(&self, searches: Vec<SavedSearch>) {
+        while let Some(child) = self.saved_list_box.first_child() {
+            self.saved_list_box.remove(&child);
+        }
+
+        for search in &searches {
+            let row = self.create_place_row(&search.name, "folder-saved-search-symbolic");
+            row.set_tooltip_text(Some(&format!(
+                "{} in {}",
+                search.search.text,
+                search.root.display()
+            )));
+
+            let remove_button = gtk::Button::from_icon_name("list-remove-symbolic");
+            remove_button.add_css_class("flat");
+            remove_button.set_tooltip_text(Some("Remove saved search"));
+            let on_saved_search_removed = self.on_saved_search_removed.clone();
+            let name = search.name.clone();
+            remove_button.connect_clicked(move |_| {
+                if let Some(callback) = on_saved_search_removed.borrow().as_ref() {
+                    callback(name.clone());
+                }
+            });
+            row.append(&remove_button);
+
+            self.saved_list_box.append(&row);
+        }
+
+        self.saved_label.set_visible(!searches.is_empty());
+        self.saved_list_box.set_visible(!searches.is_empty());
+        *self.saved_searches.borrow_mut() = searches;
+    }
+
     fn setup_activation(&self) {
         let on_location_activated = self.on_location_activated.clone();
-        let locations = self.locations.clone();
+        let on_recent_activated = self.on_recent_activated.clone();
+        let places = self.places.clone();
         self.list_box.connect_row_activated(move |_, row| {
             let index = row.index() as usize;
-            if let Some(path) = locations.borrow().get(index) {
-                if let Some(callback) = on_location_activated.borrow().as_ref() {
-                    callback(path.clone());
+            match places.borrow().get(index) {
+                Some(Place::Recent) => {
+                    if let Some(callback) = on_recent_activated.borrow().as_ref() {
+                        callback();
+                    }
+                }
+                Some(Place::Folder(path)) => {
+                    if let Some(callback) = on_location_activated.borrow().as_ref() {
+                        callback(path.clone());
+                    }
+                }
+                None => {}
+            }
+        });
+
+        let on_location_activated = self.on_location_activated.clone();
+        let bookmarks = self.bookmarks.clone();
+        self.bookmarks_list_box
+            .connect_row_activated(move |_, row| {
+                let path = bookmarks
+                    .borrow()
+                    .get(row.index() as usize)
+                    .map(|(_, path)| path.clone());
+                if let Some(path) = path {
+                    if let Some(callback) = on_location_activated.borrow().as_ref() {
+                        callback(path);
+                    }
+                }
+            });
+
+        // Unmounted devices are mounted first
+        let on_location_activated = self.on_location_activated.clone();
+        let devices = self.devices.clone();
+        self.devices_list_box
+            .connect_row_activated(move |list_box, row| {
+                let Some(device) = devices.borrow().get(row.index() as usize).cloned() else {
+                    return;
+                };
+                let Some(window) = list_box.root().and_downcast::<gtk::Window>() else {
+                    return;
+                };
+                let on_location_activated = on_location_activated.clone();
+                device.mount(&window, move |path| {
+                    if let Some(callback) = on_location_activated.borrow().as_ref() {
+                        callback(path);
+                    }
+                });
+            });
+
+        let on_saved_search_activated = self.on_saved_search_activated.clone();
+        let saved_searches = self.saved_searches.clone();
+        self.saved_list_box.connect_row_activated(move |_, row| {
+            let index = row.index() as usize;
+            let search = saved_searches.borrow().get(index).cloned();
+            if let Some(search) = search {
+                if let Some(callback) = on_saved_search_activated.borrow().as_ref() {
+                    callback(search);
                 }
             }
         });
@@ -149,10 +601,25 @@ impl Sidebar {
     {
         *self.on_location_activated.borrow_mut() = Some(Rc::new(callback));
     }
-}
 
-impl Default for Sidebar {
-    fn default() -> Self {
-        Self::new()
+    pub fn connect_recent_activated<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        *self.on_recent_activated.borrow_mut() = Some(Rc::new(callback));
+    }
+
+    pub fn connect_saved_search_activated<F>(&self, callback: F)
+    where
+        F: Fn(SavedSearch) + 'static,
+    {
+        *self.on_saved_search_activated.borrow_mut() = Some(Rc::new(callback));
+    }
+
+    pub fn connect_saved_search_removed<F>(&self, callback: F)
+    where
+        F: Fn(String) + 'static,
+    {
+        *self.on_saved_search_removed.borrow_mut() = Some(Rc::new(callback));
     }
 }
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Mutex;
+
+/// Largest buffer offered to the `get*_r` lookups before giving up.
+const MAX_ENTRY_BUFFER: usize = 1 << 20;
+
+/// User names by uid and group names by gid, looked up through NSS the first
+/// time each id is seen. Shared with the search threads.
+static USER_NAMES: Mutex<Option<HashMap<u32, String>>> = Mutex::new(None);
+static GROUP_NAMES: Mutex<Option<HashMap<u32, String>>> = Mutex::new(None);
+
+/// The name of user `uid`, or the number if it has none.
+pub fn user_name(uid: u32) -> String {
+    lookup(&USER_NAMES, uid, passwd_name)
+}
+
+/// The name of group `gid`, or the number if it has none.
+pub fn group_name(gid: u32) -> String {
+    lookup(&GROUP_NAMES, gid, group_entry_name)
+}
+
+/// Resolves a user name (or numeric uid).
+pub fn uid_for_name(user: &str) -> Option<u32> {
+    if let Ok(uid) = user.parse() {
+        return Some(uid);
+    }
+
+    let name = CString::new(user).ok()?;
+    let uid = read_entry(
+        |entry: *mut libc::passwd, buffer: &mut [libc::c_char], result| unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                entry,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                result,
+            )
+        },
+        |entry| entry.pw_uid,
+    )?;
+
+    let mut cache = USER_NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get_or_insert_with(HashMap::new)
+        .insert(uid, user.to_string());
+    Some(uid)
+}
+
+fn lookup(
+    cache: &Mutex<Option<HashMap<u32, String>>>,
+    id: u32,
+    resolve: fn(u32) -> Option<String>,
+) -> String {
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get_or_insert_with(HashMap::new)
+        .entry(id)
+        .or_insert_with(|| resolve(id).unwrap_or_else(|| id.to_string()))
+        .clone()
+}
+
+fn passwd_name(uid: u32) -> Option<String> {
+    read_entry(
+        |entry: *mut libc::passwd, buffer: &mut [libc::c_char], result| unsafe {
+            libc::getpwuid_r(uid, entry, buffer.as_mut_ptr(), buffer.len(), result)
+        },
+        |entry| {
+            unsafe { CStr::from_ptr(entry.pw_name) }
+                .to_string_lossy()
+                .to_string()
+        },
+    )
+}
+
+fn group_entry_name(gid: u32) -> Option<String> {
+    read_entry(
+        |entry: *mut libc::group, buffer: &mut [libc::c_char], result| unsafe {
+            libc::getgrgid_r(gid, entry, buffer.as_mut_ptr(), buffer.len(), result)
+        },
+        |entry| {
+            unsafe { CStr::from_ptr(entry.gr_name) }
+                .to_string_lossy()
+                .to_string()
+        },
+    )
+}
+
+/// Runs one of the reentrant `getpw*_r`/`getgr*_r` functions, growing the
+/// buffer that holds the entry's strings until they fit, and passes the
+/// entry found to `read`.
+fn read_entry<T, R, L, F>(lookup: L, read: F) -> Option<R>
+where
+    L: Fn(*mut T, &mut [libc::c_char], *mut *mut T) -> libc::c_int,
+    F: FnOnce(&T) -> R,
+{
+    let mut entry = MaybeUninit::<T>::uninit();
+    let mut buffer: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        let mut result = ptr::null_mut();
+        match lookup(entry.as_mut_ptr(), &mut buffer, &mut result) {
+            // the entry's pointers point into `buffer`, which is still alive
+            0 if !result.is_null() => return Some(read(unsafe { &*result })),
+            libc::ERANGE if buffer.len() < MAX_ENTRY_BUFFER => {
+                buffer.resize(buffer.len() * 2, 0);
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// The uid this process runs as.
+pub fn current_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+/// The groups this process belongs to, primary group first, as
+/// `(gid, name)` pairs. Includes supplementary groups from any NSS source.
+pub fn current_user_groups() -> Vec<(u32, String)> {
+    let mut gids = vec![unsafe { libc::getgid() }];
+
+    let count = unsafe { libc::getgroups(0, ptr::null_mut()) };
+    let mut supplementary: Vec<libc::gid_t> = vec![0; usize::try_from(count).unwrap_or(0)];
+    let count = unsafe {
+        libc::getgroups(
+            libc::c_int::try_from(supplementary.len()).unwrap_or(0),
+            supplementary.as_mut_ptr(),
+        )
+    };
+    supplementary.truncate(usize::try_from(count).unwrap_or(0));
+    for gid in supplementary {
+        if !gids.contains(&gid) {
+            gids.push(gid);
+        }
+    }
+
+    gids.into_iter().map(|gid| (gid, group_name(gid))).collect()
+}
@@ -0,0 +1,1262 @@
+use crate::command_palette::CommandPalette;
+use crate::file_info::FileInfo;
+use crate::file_view::{
+    FileView, ListColumn, SortColumn, ViewMode, ViewOptions, DEFAULT_ZOOM_LEVEL, MAX_ZOOM_LEVEL,
+};
+use crate::query::Query;
+use crate::quick_switcher::QuickSwitcher;
+use crate::recursive_search::{self, SearchHandle};
+use crate::saved_searches::{self, SavedSearch};
+use crate::services::{AppServices, ClipboardOperation};
+use crate::settings::Settings;
+use crate::{open_with, operations, properties, recent, search, sidebar};
+use gtk::prelude::*;
+use gtk::{Application, ApplicationWindow, Box, HeaderBar, Orientation, Paned, ScrolledWindow};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Navigation state of a single window.
+#[derive(Clone)]
+pub struct WindowState {
+    current_path: PathBuf,
+    history: Vec<PathBuf>,
+    history_index: usize,
+}
+
+/// A browser window. Each window owns its navigation state and registers its
+/// actions under `win.*`, while clipboard, bookmarks and jobs are shared
+/// through [`AppServices`].
+#[derive(Clone)]
+pub struct AppWindow {
+    window: ApplicationWindow,
+    file_view: FileView,
+    path_bar: gtk::Box,
+    items_label: gtk::Label,
+    grid_view_button: gtk::ToggleButton,
+    list_view_button: gtk::ToggleButton,
+    search_button: gtk::ToggleButton,
+    search_bar: search::SearchBar,
+    sidebar: sidebar::Sidebar,
+    search_handle: Rc<RefCell<Option<SearchHandle>>>,
+    showing_recent: Rc<Cell<bool>>,
+    quick_switcher: QuickSwitcher,
+    command_palette: CommandPalette,
+    state: Arc<Mutex<WindowState>>,
+    services: AppServices,
+}
+
+impl AppWindow {
+    pub fn new(app: &Application, services: &AppServices, initial_path: &Path) -> Self {
+        // Create header bar with navigation and view controls
+        let header = HeaderBar::new();
+
+        // Create main window
+        let window = ApplicationWindow::builder()
+            .application(app)
+            .title("zfile")
+            .default_width(1000)
+            .default_height(600)
+            .build();
+
+        // Set the header bar as titlebar
+        window.set_titlebar(Some(&header));
+
+        // Navigation buttons
+        let back_button = gtk::Button::from_icon_name("go-previous-symbolic");
+        back_button.set_action_name(Some("win.go-back"));
+        let forward_button = gtk::Button::from_icon_name("go-next-symbolic");
+        forward_button.set_action_name(Some("win.go-forward"));
+        let up_button = gtk::Button::from_icon_name("go-up-symbolic");
+        up_button.set_action_name(Some("win.go-up"));
+
+        header.pack_start(&back_button);
+        header.pack_start(&forward_button);
+        header.pack_start(&up_button);
+
+        // Path bar (breadcrumb navigation)
+        let path_bar = gtk::Box::new(Orientation::Horizontal, 4);
+        path_bar.add_css_class("linked");
+        header.set_title_widget(Some(&path_bar));
+
+        // View toggle buttons
+        let view_box = gtk::Box::new(Orientation::Horizontal, 0);
+        view_box.add_css_class("linked");
+
+        let grid_view_button = gtk::ToggleButton::builder()
+            .icon_name("view-grid-symbolic")
+            .active(true)
+            .build();
+        let list_view_button = gtk::ToggleButton::builder()
+            .icon_name("view-list-symbolic")
+            .build();
+
+        view_box.append(&grid_view_button);
+        view_box.append(&list_view_button);
+        header.pack_end(&view_box);
+
+        // Search button
+        let search_button = gtk::ToggleButton::builder()
+            .icon_name("system-search-symbolic")
+            .build();
+        header.pack_end(&search_button);
+
+        // Menu button
+        let menu_button = gtk::MenuButton::builder()
+            .icon_name("open-menu-symbolic")
+            .build();
+
+        let file_menu = gio::Menu::new();
+        file_menu.append(Some("New Window"), Some("app.new-window"));
+        file_menu.append(Some("New Folder"), Some("win.new-folder"));
+        file_menu.append(Some("New File"), Some("win.new-file"));
+        file_menu.append(Some("Bookmark This Folder"), Some("win.add-bookmark"));
+        file_menu.append(Some("Go to Folder…"), Some("win.quick-switch"));
+        file_menu.append(Some("Save Search…"), Some("win.save-search"));
+
+        let sort_menu = gio::Menu::new();
+        sort_menu.append(Some("Name"), Some("win.sort-by::name"));
+        sort_menu.append(Some("Size"), Some("win.sort-by::size"));
+        sort_menu.append(Some("Type"), Some("win.sort-by::type"));
+        sort_menu.append(Some("Modified"), Some("win.sort-by::modified"));
+        sort_menu.append(Some("Descending"), Some("win.sort-descending"));
+
+        let view_menu = gio::Menu::new();
+        let columns_menu = gio::Menu::new();
+        for column in ListColumn::ALL {
+            columns_menu.append(
+                Some(column.title()),
+                Some(&format!("win.column-{}", column.as_str())),
+            );
+        }
+
+        view_menu.append_submenu(Some("Sort By"), &sort_menu);
+        view_menu.append_submenu(Some("List Columns"), &columns_menu);
+        view_menu.append(Some("Zoom In"), Some("win.zoom-in"));
+        view_menu.append(Some("Zoom Out"), Some("win.zoom-out"));
+        view_menu.append(Some("Reset Zoom"), Some("win.zoom-reset"));
+        view_menu.append(Some("Show Hidden Files"), Some("win.show-hidden"));
+        view_menu.append(Some("Reset View to Defaults"), Some("win.reset-view"));
+        view_menu.append(
+            Some("Use These Settings for All Folders"),
+            Some("win.apply-view-to-all"),
+        );
+
+        let app_menu = gio::Menu::new();
+        app_menu.append(Some("Preferences"), Some("app.preferences"));
+        app_menu.append(Some("About"), Some("app.about"));
+
+        let menu = gio::Menu::new();
+        menu.append_section(None, &file_menu);
+        menu.append_section(None, &view_menu);
+        menu.append_section(None, &app_menu);
+        menu_button.set_menu_model(Some(&menu));
+        header.pack_end(&menu_button);
+
+        // Spinner shown while shared file operations are running
+        let jobs_spinner = gtk::Spinner::new();
+        jobs_spinner.set_tooltip_text(Some("File operations in progress"));
+        jobs_spinner.set_visible(services.jobs.active_count() > 0);
+        jobs_spinner.set_spinning(services.jobs.active_count() > 0);
+        header.pack_end(&jobs_spinner);
+
+        let spinner_weak = jobs_spinner.downgrade();
+        services.jobs.connect_changed(move |count| {
+            if let Some(spinner) = spinner_weak.upgrade() {
+                spinner.set_visible(count > 0);
+                spinner.set_spinning(count > 0);
+            }
+        });
+
+        // Main content area
+        let main_paned = Paned::new(Orientation::Horizontal);
+
+        // Sidebar
+        let sidebar = sidebar::Sidebar::new(services);
+        let sidebar_scroll = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vscrollbar_policy(gtk::PolicyType::Automatic)
+            .width_request(100)
+            .child(&sidebar.widget())
+            .build();
+
+        main_paned.set_start_child(Some(&sidebar_scroll));
+
+        // File view area
+        let file_view_container = Box::new(Orientation::Vertical, 0);
+
+        // Search bar (hidden by default)
+        let search_bar = search::SearchBar::new();
+        file_view_container.append(&search_bar.widget());
+
+        // File view (grid/list)
+        let file_view = FileView::new();
+        let file_view_scroll = ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .child(&file_view.widget())
+            .build();
+
+        file_view_container.append(&file_view_scroll);
+
+        // Ctrl+scroll zooms the file view
+        let zoom_controller =
+            gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+        file_view_scroll.add_controller(zoom_controller.clone());
+
+        // Status bar
+        let status_bar = gtk::Box::new(Orientation::Horizontal, 12);
+        status_bar.set_margin_start(12);
+        status_bar.set_margin_end(12);
+        status_bar.set_margin_top(6);
+        status_bar.set_margin_bottom(6);
+
+        let items_label = gtk::Label::new(Some("0 items"));
+        let selected_label = gtk::Label::new(None);
+
+        status_bar.append(&items_label);
+        status_bar.append(&selected_label);
+
+        file_view_container.append(&gtk::Separator::new(Orientation::Horizontal));
+        file_view_container.append(&status_bar);
+
+        main_paned.set_end_child(Some(&file_view_container));
+
+        // Set main content
+        window.set_child(Some(&main_paned));
+
+        // Window state
+        let state = Arc::new(Mutex::new(WindowState {
+            current_path: initial_path.to_path_buf(),
+            history: vec![initial_path.to_path_buf()],
+            history_index: 0,
+        }));
+
+        // Ctrl+P folder switcher, shown above the file view
+        let history_state = state.clone();
+        let quick_switcher = QuickSwitcher::new(&file_view_container, services, move || {
+            history_state
+                .try_lock()
+                .map(|state| state.history.clone())
+                .unwrap_or_default()
+        });
+
+        // Ctrl+Shift+P palette for every action of the window and application
+        let command_palette = CommandPalette::new(&file_view_container, &window, menu.upcast_ref());
+
+        let app_window = AppWindow {
+            window,
+            file_view,
+            path_bar,
+            items_label,
+            grid_view_button,
+            list_view_button,
+            search_button,
+            search_bar,
+            sidebar: sidebar.clone(),
+            search_handle: Rc::new(RefCell::new(None)),
+            showing_recent: Rc::new(Cell::new(false)),
+            quick_switcher,
+            command_palette,
+            state,
+            services: services.clone(),
+        };
+
+        // Connect signals
+        app_window.setup_actions();
+        app_window.setup_view_toggle();
+        app_window.setup_scroll_zoom(&zoom_controller);
+        app_window.setup_search();
+        app_window.setup_context_menu();
+
+        let app_window_clone = app_window.clone();
+        app_window.quick_switcher.connect_navigate(move |path| {
+            app_window_clone.navigate_to(path);
+        });
+
+        let app_window_clone = app_window.clone();
+        sidebar.connect_location_activated(move |path| {
+            app_window_clone.navigate_to(path);
+        });
+
+        let app_window_clone = app_window.clone();
+        sidebar.connect_recent_activated(move || app_window_clone.show_recent());
+
+        // Saved searches are shared by all windows' sidebars
+        sidebar.set_saved_searches(services.saved_searches.list());
+        let app_window_clone = app_window.clone();
+        sidebar.connect_saved_search_activated(move |search| {
+            app_window_clone.open_saved_search(&search);
+        });
+        let saved_searches = services.saved_searches.clone();
+        sidebar.connect_saved_search_removed(move |name| saved_searches.remove(&name));
+        let saved_searches = services.saved_searches.clone();
+        let saved_searches_handler_id = services.saved_searches.connect_changed(move || {
+            sidebar.set_saved_searches(saved_searches.list());
+        });
+
+        // Apply preferences, which reload nothing until the initial directory
+        // is loaded
+        app_window.apply_settings_to_view(&services.settings.get());
+        app_window.show_directory(initial_path);
+
+        // Keep following the preferences while the window is open. Folders
+        // without their own view settings pick up changed defaults, unless
+        // search results or recent files are shown.
+        let app_window_clone = app_window.clone();
+        let previous_settings = RefCell::new(services.settings.get());
+        let handler_id = services.settings.connect_changed(move |settings| {
+            app_window_clone.apply_settings_to_view(settings);
+
+            let previous = previous_settings.replace(settings.clone());
+            let defaults_changed =
+                previous.default_view_options() != settings.default_view_options();
+            let showing_results = app_window_clone.showing_recent.get()
+                || app_window_clone.file_view.is_showing_results();
+            if defaults_changed && !showing_results {
+                app_window_clone.show_directory(&app_window_clone.file_view.current_path());
+            }
+        });
+        // The recent files list also changes when other applications open files
+        let recent_manager = gtk::RecentManager::default();
+        let app_window_clone = app_window.clone();
+        let recent_handler_id = recent_manager.connect_changed(move |_| {
+            if app_window_clone.showing_recent.get() {
+                app_window_clone.show_recent();
+            }
+        });
+
+        let settings_store = services.settings.clone();
+        let saved_searches = services.saved_searches.clone();
+        let sidebar_clone = app_window.sidebar.clone();
+        app_window.window.connect_destroy(move |_| {
+            settings_store.disconnect(handler_id);
+            saved_searches.disconnect(saved_searches_handler_id);
+            recent_manager.disconnect(recent_handler_id);
+            sidebar_clone.disconnect();
+        });
+
+        // Handle file activation (double-click)
+        let app_window_clone = app_window.clone();
+        app_window.file_view.connect_activated(move |path| {
+            app_window_clone.open_path(&path);
+        });
+
+        app_window
+    }
+
+    pub fn window(&self) -> ApplicationWindow {
+        self.window.clone()
+    }
+
+    pub fn file_view(&self) -> FileView {
+        self.file_view.clone()
+    }
+
+    /// Loads `path` and records it in the window's history.
+    pub fn navigate_to(&self, path: PathBuf) {
+        self.show_directory(&path);
+
+        let state = self.state.clone();
+        glib::spawn_future_local(async move {
+            let mut state = state.lock().await;
+            let history_index = state.history_index;
+            state.history.truncate(history_index + 1);
+            state.history.push(path.clone());
+            state.history_index = state.history.len() - 1;
+            state.current_path = path;
+        });
+    }
+
+    fn apply_settings_to_view(&self, settings: &Settings) {
+        self.file_view.set_hide_backups(settings.hide_backup_files);
+        self.file_view.set_single_click(settings.single_click);
+        self.file_view.set_show_thumbnails(settings.show_thumbnails);
+        self.file_view.set_size_format(settings.size_format());
+        self.file_view.set_folder_sizes(
+            settings
+                .folder_sizes
+                .then(|| self.services.folder_sizes.clone()),
+        );
+        self.file_view
+            .set_list_columns(settings.list_columns.clone());
+        for column in ListColumn::ALL {
+            let shown = settings.list_columns.contains(&column);
+            self.set_action_state(&format!("column-{}", column.as_str()), &shown.to_variant());
+        }
+        update_status(&self.items_label, &self.file_view);
+    }
+
+    /// Shows or hides hidden files in the current folder and remembers the
+    /// choice for it.
+    fn set_show_hidden(&self, show_hidden: bool) {
+        self.file_view.set_show_hidden(show_hidden);
+        self.save_folder_view();
+        update_status(&self.items_label, &self.file_view);
+    }
+
+    fn set_sort(&self, column: SortColumn, descending: bool) {
+        self.file_view.set_sort(column, descending);
+        self.save_folder_view();
+    }
+
+    /// Stores the current view options as the settings of the current folder.
+    fn save_folder_view(&self) {
+        let options = self.file_view.view_options();
+        self.services
+            .folder_views
+            .set(&self.file_view.current_path(), options);
+        self.sync_view_controls(&options);
+    }
+
+    /// Loads `path` with its remembered view options, or the defaults.
+    fn show_directory(&self, path: &Path) {
+        *self.search_handle.borrow_mut() = None;
+        self.showing_recent.set(false);
+
+        if self.file_view.current_path() != path {
+            self.services.locations.record(path);
+        }
+
+        let options = self
+            .services
+            .folder_views
+            .get(path)
+            .unwrap_or_else(|| self.services.settings.get().default_view_options());
+        self.file_view.set_view_options(options);
+        self.sync_view_controls(&options);
+
+        self.file_view.load_directory(path);
+        update_path_bar(&self.path_bar, path);
+        update_status(&self.items_label, &self.file_view);
+        self.sidebar.set_current_location(path);
+    }
+
+    /// Updates toggle buttons and stateful actions to reflect `options`.
+    fn sync_view_controls(&self, options: &ViewOptions) {
+        match options.mode {
+            ViewMode::Grid => self.grid_view_button.set_active(true),
+            ViewMode::List => self.list_view_button.set_active(true),
+        }
+
+        self.set_action_state("show-hidden", &options.show_hidden.to_variant());
+        self.set_action_state("sort-by", &options.sort_column.as_str().to_variant());
+        self.set_action_state("sort-descending", &options.sort_descending.to_variant());
+        self.set_action_enabled("zoom-in", options.zoom_level < MAX_ZOOM_LEVEL);
+        self.set_action_enabled("zoom-out", options.zoom_level > 0);
+    }
+
+    fn set_action_enabled(&self, name: &str, enabled: bool) {
+        if let Some(action) = self
+            .window
+            .lookup_action(name)
+            .and_downcast::<gio::SimpleAction>()
+        {
+            action.set_enabled(enabled);
+        }
+    }
+
+    fn set_action_state(&self, name: &str, state: &glib::Variant) {
+        if let Some(action) = self
+            .window
+            .lookup_action(name)
+            .and_downcast::<gio::SimpleAction>()
+        {
+            action.set_state(state);
+        }
+    }
+
+    /// Moves `offset` steps through the history, if there is an entry there.
+    fn go_history(&self, offset: isize) {
+        let app_window = self.clone();
+        glib::spawn_future_local(async move {
+            let mut state = app_window.state.lock().await;
+            let Some(index) = state.history_index.checked_add_signed(offset) else {
+                return;
+            };
+            let Some(path) = state.history.get(index).cloned() else {
+                return;
+            };
+            state.history_index = index;
+            state.current_path = path.clone();
+            drop(state);
+
+            app_window.show_directory(&path);
+        });
+    }
+
+    fn setup_view_toggle(&self) {
+        let app_window = self.clone();
+        self.grid_view_button.connect_toggled(move |button| {
+            if button.is_active() {
+                app_window.list_view_button.set_active(false);
+                app_window.set_view_mode(ViewMode::Grid);
+            }
+        });
+
+        let app_window = self.clone();
+        self.list_view_button.connect_toggled(move |button| {
+            if button.is_active() {
+                app_window.grid_view_button.set_active(false);
+                app_window.set_view_mode(ViewMode::List);
+            }
+        });
+    }
+
+    fn setup_scroll_zoom(&self, controller: &gtk::EventControllerScroll) {
+        let app_window = self.clone();
+        controller.connect_scroll(move |controller, _, dy| {
+            if !controller
+                .current_event_state()
+                .contains(gdk::ModifierType::CONTROL_MASK)
+            {
+                return glib::Propagation::Proceed;
+            }
+
+            if dy < 0.0 {
+                app_window.zoom_by(1);
+            } else if dy > 0.0 {
+                app_window.zoom_by(-1);
+            }
+            glib::Propagation::Stop
+        });
+    }
+
+    fn zoom_by(&self, delta: i32) {
+        if self.file_view.zoom_by(delta) {
+            self.save_folder_view();
+        }
+    }
+
+    fn setup_search(&self) {
+        let app_window = self.clone();
+        self.search_button.connect_toggled(move |button| {
+            app_window.search_bar.set_visible(button.is_active());
+            if button.is_active() {
+                app_window.search_bar.grab_focus();
+            } else {
+                app_window.clear_search();
+            }
+        });
+
+        // Search as the user types
+        let app_window = self.clone();
+        self.search_bar
+            .connect_search(move |text| app_window.run_search(&text));
+    }
+
+    /// Filters the current folder by `text`, or searches its subfolders in the
+    /// background when the search bar asks for a recursive or content search.
+    fn run_search(&self, text: &str) {
+        // A new query supersedes any running search
+        *self.search_handle.borrow_mut() = None;
+
+        if text.is_empty() {
+            self.search_bar.set_error(None);
+            self.clear_search();
+            return;
+        }
+
+        let query = match Query::parse(text) {
+            Ok(query) => query,
+            Err(e) => {
+                self.search_bar.set_error(Some(&e.to_string()));
+                return;
+            }
+        };
+
+        let root = self.file_view.current_path();
+        if self.search_bar.searches_contents() && !query.text.is_empty() {
+            let pattern = match search::content_regex(&query.text, self.search_bar.mode()) {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    self.search_bar.set_error(Some(&e.to_string()));
+                    return;
+                }
+            };
+            self.search_bar.set_error(None);
+            self.begin_background_search(&root);
+
+            let app_window = self.clone();
+            let finished_window = self.clone();
+            let handle = recursive_search::spawn_content_search(
+                root,
+                pattern,
+                query,
+                self.search_bar.walk_options(),
+                move |matches| {
+                    app_window.file_view.append_content_results(matches);
+                    app_window.update_search_status(false);
+                },
+                move || finished_window.update_search_status(true),
+            );
+            *self.search_handle.borrow_mut() = Some(handle);
+            return;
+        }
+
+        // Filters alone match every name
+        let mode = if query.text.is_empty() {
+            search::SearchMode::Substring
+        } else {
+            self.search_bar.mode()
+        };
+        let matcher = match search::NameMatcher::new(&query.text, mode) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.search_bar.set_error(Some(&e.to_string()));
+                return;
+            }
+        };
+        self.search_bar.set_error(None);
+
+        if self.search_bar.is_recursive() || self.search_bar.searches_contents() {
+            let walk_options = self.search_bar.walk_options();
+            self.begin_background_search(&root);
+
+            let app_window = self.clone();
+            let finished_window = self.clone();
+            let on_results = move |results: Vec<FileInfo>| {
+                app_window.file_view.append_results(results);
+                app_window.update_search_status(false);
+            };
+            // Indexed folders are searched without walking the disk
+            let handle = if self.services.file_index.can_search(&root, walk_options) {
+                self.services.file_index.search(
+                    root,
+                    matcher,
+                    query,
+                    walk_options,
+                    on_results,
+                    move || {
+                        finished_window.items_label.set_text(&format!(
+                            "{} results from the index",
+                            finished_window.file_view.item_count()
+                        ))
+                    },
+                )
+            } else {
+                recursive_search::spawn_search(
+                    root,
+                    matcher,
+                    query,
+                    walk_options,
+                    on_results,
+                    move || finished_window.update_search_status(true),
+                )
+            };
+            *self.search_handle.borrow_mut() = Some(handle);
+        } else {
+            if self.file_view.is_showing_results() {
+                self.file_view.load_directory(&root);
+            }
+            self.file_view
+                .set_filter(Some(Rc::new(move |file: &FileInfo| {
+                    matcher.is_match(&file.name) && query.matches(file)
+                })));
+            update_status(&self.items_label, &self.file_view);
+        }
+    }
+
+    fn begin_background_search(&self, root: &Path) {
+        self.file_view.begin_results(root);
+        self.items_label.set_text("Searching…");
+    }
+
+    fn update_search_status(&self, finished: bool) {
+        let count = self.file_view.item_count();
+        if finished {
+            self.items_label.set_text(&format!("{} results", count));
+        } else {
+            self.items_label
+                .set_text(&format!("Searching… {} results", count));
+        }
+    }
+
+    /// Stops searching and restores the full listing of the current folder.
+    fn clear_search(&self) {
+        *self.search_handle.borrow_mut() = None;
+
+        if self.file_view.is_showing_results() {
+            self.show_directory(&self.file_view.current_path());
+        } else {
+            self.file_view.set_filter(None);
+            update_status(&self.items_label, &self.file_view);
+        }
+    }
+
+    /// Shows the results of `search` in its folder, like opening a folder.
+    fn open_saved_search(&self, search: &SavedSearch) {
+        self.navigate_to(search.root.clone());
+        self.search_button.set_active(true);
+        self.search_bar.set_state(&search.search);
+    }
+
+    fn save_search(&self) {
+        let state = self.search_bar.state();
+        if state.text.is_empty() {
+            return;
+        }
+
+        let root = self.file_view.current_path();
+        let saved_searches = self.services.saved_searches.clone();
+        saved_searches::show_save_search_dialog(&self.window, &state.text, move |name| {
+            saved_searches.add(SavedSearch {
+                name,
+                root: root.clone(),
+                search: state.clone(),
+            });
+        });
+    }
+
+    /// Lists recently used files in place of a folder. The current folder is
+    /// kept, so going up or back returns to it.
+    fn show_recent(&self) {
+        *self.search_handle.borrow_mut() = None;
+        self.showing_recent.set(true);
+
+        let root = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+        self.file_view.begin_results(&root);
+        self.file_view.append_annotated_results(
+            recent::list()
+                .into_iter()
+                .map(|recent_file| {
+                    let description = recent_file.description();
+                    (recent_file.file, description)
+                })
+                .collect(),
+        );
+
+        while let Some(child) = self.path_bar.first_child() {
+            self.path_bar.remove(&child);
+        }
+        let recent_button = gtk::Button::with_label("Recent");
+        recent_button.add_css_class("flat");
+        self.path_bar.append(&recent_button);
+
+        self.items_label
+            .set_text(&format!("{} recent files", self.file_view.item_count()));
+        self.sidebar.select_recent();
+    }
+
+    /// Reloads after a file operation, re-running the search when showing
+    /// search results so they stay in place.
+    fn refresh(&self) {
+        if self.showing_recent.get() {
+            self.show_recent();
+        } else if self.file_view.is_showing_results() {
+            self.run_search(&self.search_bar.get_text());
+        } else {
+            self.file_view.reload();
+            update_status(&self.items_label, &self.file_view);
+        }
+    }
+
+    fn set_view_mode(&self, mode: ViewMode) {
+        if self.file_view.view_options().mode != mode {
+            self.file_view.set_view_mode(mode);
+            self.save_folder_view();
+        }
+    }
+
+    fn setup_actions(&self) {
+        // Navigation actions
+        let app_window = self.clone();
+        self.add_action("go-back", move || app_window.go_history(-1));
+
+        let app_window = self.clone();
+        self.add_action("go-forward", move || app_window.go_history(1));
+
+        let app_window = self.clone();
+        self.add_action("go-up", move || {
+            let app_window = app_window.clone();
+            glib::spawn_future_local(async move {
+                let current_path = {
+                    let state = app_window.state.lock().await;
+                    state.current_path.clone()
+                };
+
+                if let Some(parent) = current_path.parent() {
+                    app_window.navigate_to(parent.to_path_buf());
+                }
+            });
+        });
+
+        // View actions, remembered per folder
+        let app_window = self.clone();
+        self.add_toggle_action("show-hidden", move |show_hidden| {
+            app_window.set_show_hidden(show_hidden);
+        });
+
+        let sort_by_action = gio::SimpleAction::new_stateful(
+            "sort-by",
+            Some(glib::VariantTy::STRING),
+            &SortColumn::Name.as_str().to_variant(),
+        );
+        let app_window = self.clone();
+        sort_by_action.connect_activate(move |_, parameter| {
+            let column = parameter
+                .and_then(|p| p.str())
+                .and_then(SortColumn::from_name);
+            if let Some(column) = column {
+                let descending = app_window.file_view.view_options().sort_descending;
+                app_window.set_sort(column, descending);
+            }
+        });
+        self.window.add_action(&sort_by_action);
+
+        let app_window = self.clone();
+        self.add_toggle_action("sort-descending", move |descending| {
+            let column = app_window.file_view.view_options().sort_column;
+            app_window.set_sort(column, descending);
+        });
+
+        let app_window = self.clone();
+        self.add_action("zoom-in", move || app_window.zoom_by(1));
+
+        let app_window = self.clone();
+        self.add_action("zoom-out", move || app_window.zoom_by(-1));
+
+        let app_window = self.clone();
+        self.add_action("zoom-reset", move || {
+            if app_window.file_view.set_zoom_level(DEFAULT_ZOOM_LEVEL) {
+                app_window.save_folder_view();
+            }
+        });
+
+        let app_window = self.clone();
+        self.add_action("reset-view", move || {
+            let current_path = app_window.file_view.current_path();
+            app_window.services.folder_views.remove(&current_path);
+            app_window.show_directory(&current_path);
+        });
+
+        let app_window = self.clone();
+        self.add_action("apply-view-to-all", move || {
+            let options = app_window.file_view.view_options();
+            app_window.services.folder_views.clear();
+            app_window
+                .services
+                .settings
+                .update(|s| s.set_default_view_options(options));
+        });
+
+        // New folder action
+        let app_window = self.clone();
+        self.add_action("new-folder", move || {
+            let app_window = app_window.clone();
+            glib::spawn_future_local(async move {
+                let current_path = {
+                    let state = app_window.state.lock().await;
+                    state.current_path.clone()
+                };
+
+                operations::show_new_folder_dialog(&app_window.window, &current_path);
+            });
+        });
+
+        // New file action
+        let app_window = self.clone();
+        self.add_action("new-file", move || {
+            let app_window = app_window.clone();
+            glib::spawn_future_local(async move {
+                let current_path = {
+                    let state = app_window.state.lock().await;
+                    state.current_path.clone()
+                };
+
+                operations::show_new_file_dialog(&app_window.window, &current_path);
+            });
+        });
+
+        // Clipboard actions, shared between windows
+        let app_window = self.clone();
+        self.add_action("copy", move || {
+            app_window.set_clipboard(ClipboardOperation::Copy);
+        });
+
+        let app_window = self.clone();
+        self.add_action("cut", move || {
+            app_window.set_clipboard(ClipboardOperation::Cut);
+        });
+
+        let app_window = self.clone();
+        self.add_action("paste", move || app_window.paste());
+
+        // Delete action
+        let app_window = self.clone();
+        self.add_action("delete", move || app_window.delete_selection());
+
+        // Opening files
+        let app_window = self.clone();
+        self.add_action("open", move || {
+            for path in app_window.file_view.selected_paths() {
+                app_window.open_path(&path);
+            }
+        });
+
+        let open_with_action = gio::SimpleAction::new("open-with", Some(glib::VariantTy::STRING));
+        let app_window = self.clone();
+        open_with_action.connect_activate(move |_, parameter| {
+            let Some(app_id) = parameter.and_then(|p| p.get::<String>()) else {
+                return;
+            };
+            let paths = app_window.file_view.selected_paths();
+            let Some(first) = paths.first() else {
+                return;
+            };
+            if let Some(app) = open_with::application_by_id(first, &app_id) {
+                open_with::launch(&app_window.window, &app, &paths);
+            }
+        });
+        self.window.add_action(&open_with_action);
+
+        let app_window = self.clone();
+        self.add_action("open-with-other", move || {
+            if let Some(path) = app_window.file_view.selected_paths().first() {
+                open_with::show_open_with_dialog(&app_window.window, path);
+            }
+        });
+
+        // Optional list columns, shared by all folders
+        for column in ListColumn::ALL {
+            let settings = self.services.settings.clone();
+            self.add_toggle_action(&format!("column-{}", column.as_str()), move |shown| {
+                settings.update(|s| {
+                    s.list_columns.retain(|c| *c != column);
+                    if shown {
+                        s.list_columns.push(column);
+                    }
+                });
+            });
+        }
+
+        // Symbolic links
+        let app_window = self.clone();
+        self.add_action("open-link-target", move || {
+            app_window.open_link_target_location();
+        });
+
+        let app_window = self.clone();
+        self.add_action("create-link", move || app_window.create_links());
+
+        let app_window = self.clone();
+        self.add_action("properties", move || {
+            let path = app_window
+                .file_view
+                .selected_paths()
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| app_window.file_view.current_path());
+            app_window.show_properties(&path);
+        });
+
+        let app_window = self.clone();
+        self.add_action("save-search", move || app_window.save_search());
+
+        let app_window = self.clone();
+        self.add_action("add-bookmark", move || {
+            let current_path = app_window.file_view.current_path();
+            app_window.services.bookmarks.add(&current_path);
+        });
+
+        let app_window = self.clone();
+        self.add_action("quick-switch", move || app_window.quick_switcher.popup());
+
+        let app_window = self.clone();
+        self.add_action("command-palette", move || {
+            app_window.command_palette.popup()
+        });
+    }
+
+    fn add_action<F>(&self, name: &str, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        let action = gio::SimpleAction::new(name, None);
+        action.connect_activate(move |_, _| callback());
+        self.window.add_action(&action);
+    }
+
+    /// Adds a boolean stateful action; `callback` receives the toggled value.
+    fn add_toggle_action<F>(&self, name: &str, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        let action = gio::SimpleAction::new_stateful(name, None, &false.to_variant());
+        action.connect_activate(move |action, _| {
+            let active = action
+                .state()
+                .and_then(|s| s.get::<bool>())
+                .unwrap_or(false);
+            callback(!active);
+        });
+        self.window.add_action(&action);
+    }
+
+    /// Enters folders and opens files with their default application.
+    fn open_path(&self, path: &Path) {
+        if path.is_dir() {
+            self.navigate_to(path.to_path_buf());
+        } else {
+            open_with::open_default(&self.window, path);
+        }
+    }
+
+    /// Right-clicking an item selects it and shows what can be done with the
+    /// selection; right-clicking empty space offers folder actions.
+    fn setup_context_menu(&self) {
+        let click = gtk::GestureClick::new();
+        click.set_button(gdk::BUTTON_SECONDARY);
+        let app_window = self.clone();
+        click.connect_pressed(move |_, _, x, y| {
+            let file_view_widget = app_window.file_view.widget();
+            app_window.file_view.select_item_at(x, y);
+
+            let popover = gtk::PopoverMenu::from_model(Some(&app_window.context_menu_model()));
+            popover.set_parent(&file_view_widget);
+            popover.set_has_arrow(false);
+            popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+            popover.connect_closed(|popover| {
+                let popover = popover.clone();
+                glib::idle_add_local_once(move || popover.unparent());
+            });
+            popover.popup();
+        });
+        self.file_view.widget().add_controller(click);
+    }
+
+    fn context_menu_model(&self) -> gio::Menu {
+        let menu = gio::Menu::new();
+        let paths = self.file_view.selected_paths();
+
+        if paths.is_empty() {
+            let create_section = gio::Menu::new();
+            create_section.append(Some("New Folder"), Some("win.new-folder"));
+            create_section.append(Some("New File"), Some("win.new-file"));
+            menu.append_section(None, &create_section);
+
+            let clipboard_section = gio::Menu::new();
+            clipboard_section.append(Some("Paste"), Some("win.paste"));
+            menu.append_section(None, &clipboard_section);
+
+            let properties_section = gio::Menu::new();
+            properties_section.append(Some("Properties"), Some("win.properties"));
+            menu.append_section(None, &properties_section);
+            return menu;
+        }
+
+        let open_section = gio::Menu::new();
+        open_section.append(Some("Open"), Some("win.open"));
+        let files_only = paths.iter().all(|path| !path.is_dir());
+        if files_only {
+            let open_with_menu = gio::Menu::new();
+            for app in open_with::applications_for(&paths[0]) {
+                let Some(id) = app.id() else {
+                    continue;
+                };
+                let item = gio::MenuItem::new(Some(&app.display_name()), None);
+                item.set_action_and_target_value(
+                    Some("win.open-with"),
+                    Some(&id.to_string().to_variant()),
+                );
+                if let Some(icon) = app.icon() {
+                    item.set_icon(&icon);
+                }
+                open_with_menu.append_item(&item);
+            }
+            if paths.len() == 1 {
+                let other_section = gio::Menu::new();
+                other_section.append(Some("Other Application…"), Some("win.open-with-other"));
+                open_with_menu.append_section(None, &other_section);
+            }
+            open_section.append_submenu(Some("Open With"), &open_with_menu);
+        }
+        if let [file] = self.file_view.selected_files().as_slice() {
+            if file.is_symlink() {
+                open_section.append(
+                    Some("Open Link Target Location"),
+                    Some("win.open-link-target"),
+                );
+            }
+        }
+        menu.append_section(None, &open_section);
+
+        let clipboard_section = gio::Menu::new();
+        clipboard_section.append(Some("Cut"), Some("win.cut"));
+        clipboard_section.append(Some("Copy"), Some("win.copy"));
+        clipboard_section.append(Some("Paste"), Some("win.paste"));
+        clipboard_section.append(Some("Create Link"), Some("win.create-link"));
+        menu.append_section(None, &clipboard_section);
+
+        let delete_section = gio::Menu::new();
+        delete_section.append(Some("Move to Trash"), Some("win.delete"));
+        menu.append_section(None, &delete_section);
+
+        let properties_section = gio::Menu::new();
+        properties_section.append(Some("Properties"), Some("win.properties"));
+        menu.append_section(None, &properties_section);
+
+        menu
+    }
+
+    /// Shows the folder containing the selected link's target, with the
+    /// target selected.
+    fn open_link_target_location(&self) {
+        let Some(target) = self
+            .file_view
+            .selected_files()
+            .first()
+            .and_then(|file| file.resolved_link_target())
+        else {
+            return;
+        };
+        let Some(folder) = target.parent() else {
+            return;
+        };
+        if !folder.is_dir() {
+            operations::show_error_dialog(
+                &self.window,
+                "Cannot open link target",
+                &format!("{} does not exist.", folder.display()),
+            );
+            return;
+        }
+
+        self.navigate_to(folder.to_path_buf());
+        self.file_view.select_paths(&[target.clone()]);
+    }
+
+    /// Shows the properties dialog for `path`, reloading if it gets changed.
+    pub fn show_properties(&self, path: &Path) {
+        let app_window = self.clone();
+        properties::show_properties_dialog(&self.window, &self.services, path, move || {
+            app_window.refresh()
+        });
+    }
+
+    /// Links the selected items into the current folder.
+    fn create_links(&self) {
+        let paths = self.file_view.selected_paths();
+        if paths.is_empty() {
+            return;
+        }
+
+        let destination = self.file_view.current_path();
+        if let Err(e) = operations::create_links(&paths, &destination) {
+            operations::show_error_dialog(&self.window, "Failed to create link", &e);
+        }
+        self.refresh();
+    }
+
+    fn set_clipboard(&self, operation: ClipboardOperation) {
+        let paths = self.file_view.selected_paths();
+        if !paths.is_empty() {
+            self.services.clipboard.set(operation, paths);
+        }
+    }
+
+    fn delete_selection(&self) {
+        let paths = self.file_view.selected_paths();
+        if paths.is_empty() {
+            return;
+        }
+
+        let app_window = self.clone();
+        let job_paths = paths.clone();
+        let delete = move || {
+            let finished_window = app_window.clone();
+            app_window.services.jobs.push(
+                move || operations::delete_files(&job_paths),
+                move |result| {
+                    if let Err(e) = result {
+                        operations::show_error_dialog(
+                            &finished_window.window,
+                            "Failed to delete",
+                            &e,
+                        );
+                    }
+                    finished_window.refresh();
+                },
+            );
+        };
+
+        if self.services.settings.get().confirm_delete {
+            operations::show_delete_confirmation(&self.window, &paths, delete);
+        } else {
+            delete();
+        }
+    }
+
+    fn paste(&self) {
+        let Some(contents) = self.services.clipboard.get() else {
+            return;
+        };
+
+        let destination = self.file_view.current_path();
+        let job_destination = destination.clone();
+        let job_contents = contents.clone();
+        let app_window = self.clone();
+        self.services.jobs.push(
+            move || match job_contents.operation {
+                ClipboardOperation::Copy => {
+                    operations::copy_files(&job_contents.paths, &job_destination)
+                }
+                ClipboardOperation::Cut => {
+                    operations::move_files(&job_contents.paths, &job_destination)
+                }
+            },
+            move |result| {
+                match result {
+                    Ok(()) => app_window.services.clipboard.finish_paste(&contents),
+                    Err(e) => {
+                        operations::show_error_dialog(&app_window.window, "Failed to paste", &e)
+                    }
+                }
+                if app_window.file_view.current_path() == destination {
+                    app_window.refresh();
+                }
+            },
+        );
+    }
+}
+
+fn update_path_bar(path_bar: &gtk::Box, path: &Path) {
+    // Clear existing buttons
+    while let Some(child) = path_bar.first_child() {
+        path_bar.remove(&child);
+    }
+
+    let components: Vec<_> = path.components().collect();
+
+    for (i, component) in components.iter().enumerate() {
+        let component_str = component.as_os_str().to_string_lossy().to_string();
+        let display_name = if component_str.is_empty() || component_str == "/" {
+            "/".to_string()
+        } else {
+            component_str
+        };
+
+        let button = gtk::Button::with_label(&display_name);
+        button.add_css_class("flat");
+
+        path_bar.append(&button);
+
+        if i < components.len() - 1 {
+            let separator = gtk::Label::new(Some("/"));
+            separator.add_css_class("dim-label");
+            path_bar.append(&separator);
+        }
+    }
+}
+
+fn update_status(items_label: &gtk::Label, file_view: &FileView) {
+    let count = file_view.item_count();
+    let hidden_count = file_view.hidden_count();
+    if hidden_count > 0 {
+        items_label.set_text(&format!("{} items, {} hidden", count, hidden_count));
+    } else {
+        items_label.set_text(&format!("{} items", count));
+    }
+}
@@ -0,0 +1,229 @@
+//! Runs zfile on a private session bus and talks to its
+//! `org.freedesktop.FileManager1` service like another application would.
+//!
+//! Needs `dbus-daemon` and a display to open windows on; the test is skipped
+//! without them.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const BUS_NAME: &str = "org.freedesktop.FileManager1";
+const OBJECT_PATH: &str = "/org/freedesktop/FileManager1";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Kills the child process when the test ends, also on failure.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn has_display() -> bool {
+    env::var_os("DISPLAY").is_some() || env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Starts a private bus and returns it with its address.
+fn start_bus() -> Option<(KillOnDrop, String)> {
+    let mut daemon = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let stdout = daemon.stdout.take()?;
+    let daemon = KillOnDrop(daemon);
+
+    let mut address = String::new();
+    BufReader::new(stdout).read_line(&mut address).ok()?;
+    let address = address.trim().to_string();
+    (!address.is_empty()).then_some((daemon, address))
+}
+
+/// A fresh folder under the system temp dir, so zfile's settings, bookmarks
+/// and recent files don't touch the user's.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("zfile-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn start_zfile(bus_address: &str, home: &Path) -> KillOnDrop {
+    let child = Command::new(env!("CARGO_BIN_EXE_zfile"))
+        .env("DBUS_SESSION_BUS_ADDRESS", bus_address)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .spawn()
+        .expect("failed to start zfile");
+    KillOnDrop(child)
+}
+
+fn connect(bus_address: &str) -> gio::DBusConnection {
+    gio::DBusConnection::for_address_sync(
+        bus_address,
+        gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+            | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+        None,
+        gio::Cancellable::NONE,
+    )
+    .expect("failed to connect to the private bus")
+}
+
+fn wait_for_name(connection: &gio::DBusConnection) {
+    let started = Instant::now();
+    loop {
+        let has_owner = connection
+            .call_sync(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "NameHasOwner",
+                Some(&(BUS_NAME,).into()),
+                Some(glib::VariantTy::new("(b)").unwrap()),
+                gio::DBusCallFlags::NONE,
+                -1,
+                gio::Cancellable::NONE,
+            )
+            .ok()
+            .and_then(|reply| reply.get::<(bool,)>())
+            .map(|(has_owner,)| has_owner)
+            .unwrap_or(false);
+        if has_owner {
+            return;
+        }
+        assert!(
+            started.elapsed() < STARTUP_TIMEOUT,
+            "zfile did not take {} on the bus",
+            BUS_NAME
+        );
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn call(
+    connection: &gio::DBusConnection,
+    method: &str,
+    parameters: glib::Variant,
+) -> Result<glib::Variant, glib::Error> {
+    connection.call_sync(
+        Some(BUS_NAME),
+        OBJECT_PATH,
+        BUS_NAME,
+        method,
+        Some(&parameters),
+        None,
+        gio::DBusCallFlags::NONE,
+        10_000,
+        gio::Cancellable::NONE,
+    )
+}
+
+/// Waits until a window has shown `folder`, which records it among the
+/// visited locations in `$XDG_DATA_HOME/zfile/locations.json`.
+fn wait_for_visit(home: &Path, folder: &Path) {
+    let locations = home.join("data").join("zfile").join("locations.json");
+    let key = folder.to_string_lossy().to_string();
+    let started = Instant::now();
+    loop {
+        let visited = fs::read_to_string(&locations)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+            .is_some_and(|visits| visits.get(&key).is_some());
+        if visited {
+            return;
+        }
+        assert!(
+            started.elapsed() < STARTUP_TIMEOUT,
+            "no window showed {}",
+            folder.display()
+        );
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn uri(path: &Path) -> String {
+    gio::File::for_path(path).uri().to_string()
+}
+
+#[test]
+fn file_manager1_service() {
+    if !has_display() {
+        eprintln!("skipping: no display to open windows on");
+        return;
+    }
+    let Some((_bus, bus_address)) = start_bus() else {
+        eprintln!("skipping: dbus-daemon is not available");
+        return;
+    };
+
+    let home = scratch_dir("file-manager1");
+    let folder = home.join("folder");
+    fs::create_dir_all(&folder).unwrap();
+    let item = folder.join("item.txt");
+    fs::write(&item, "zfile").unwrap();
+
+    let _zfile = start_zfile(&bus_address, &home);
+    let connection = connect(&bus_address);
+    wait_for_name(&connection);
+
+    for method in ["ShowFolders", "ShowItems", "ShowItemProperties"] {
+        let target = if method == "ShowFolders" {
+            &folder
+        } else {
+            &item
+        };
+        let parameters = (vec![uri(target)], String::new()).into();
+        let reply = call(&connection, method, parameters);
+        assert!(reply.is_ok(), "{} failed: {:?}", method, reply.err());
+        if method == "ShowFolders" {
+            wait_for_visit(&home, &folder);
+        }
+    }
+
+    // Items open their parent folder
+    let other = home.join("other");
+    fs::create_dir_all(&other).unwrap();
+    let other_item = other.join("item.txt");
+    fs::write(&other_item, "zfile").unwrap();
+    let parameters = (vec![uri(&other_item)], String::new()).into();
+    assert!(call(&connection, "ShowItems", parameters).is_ok());
+    wait_for_visit(&home, &other);
+
+    // Plain paths are accepted too, and missing items don't fail the call
+    let parameters = (
+        vec![
+            folder.to_string_lossy().to_string(),
+            uri(&home.join("missing")),
+        ],
+        "startup-id".to_string(),
+    )
+        .into();
+    assert!(call(&connection, "ShowItems", parameters).is_ok());
+
+    let unknown = call(
+        &connection,
+        "Frobnicate",
+        (vec![uri(&folder)], String::new()).into(),
+    );
+    assert!(unknown.is_err(), "unknown methods must be rejected");
+
+    let bad_signature = call(&connection, "ShowFolders", (uri(&folder),).into());
+    assert!(bad_signature.is_err(), "(s) must be rejected for (as, s)");
+
+    let missing_argument = call(&connection, "ShowItems", (vec![uri(&item)],).into());
+    assert!(
+        missing_argument.is_err(),
+        "(as) must be rejected for (as, s)"
+    );
+
+    let _ = fs::remove_dir_all(&home);
+}